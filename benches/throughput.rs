@@ -0,0 +1,273 @@
+//! Throughput of the applets on synthetic input, with the system's own
+//! tools alongside when they are installed.
+//!
+//!     cargo bench --bench throughput [FILTER]
+//!
+//! Only benchmarks whose name contains FILTER are run. RTW_BENCH_MB sets
+//! the size of each input (default 16), RTW_BENCH_RUNS how many times
+//! each is timed; the best run is reported.
+extern crate rtw;
+
+use rtw::cat::CatBuilder;
+use rtw::echo::EchoBuilder;
+use rtw::printf::PrintfBuilder;
+use rtw::{Context, Utility};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// The cat option sets measured, as command line arguments.
+const CAT_OPTIONS: &[&[&str]] = &[
+    &[],
+    &["-n"],
+    &["-b"],
+    &["-s"],
+    &["-E"],
+    &["-T"],
+    &["-v"],
+    &["-A"],
+    &["-ns"],
+    &["-bA"],
+];
+
+/// A small xorshift generator, so that inputs are the same on every run.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+/// Printable text with a line end about every `line` bytes and, one line
+/// in `blank_every`, a run of blank lines.
+fn text(size: usize, line: u64, blank_every: u64, rng: &mut Rng) -> Vec<u8> {
+    let mut data = Vec::with_capacity(size + line as usize);
+    while data.len() < size {
+        let len = line / 2 + rng.below(line);
+        for _ in 0..len {
+            data.push(match rng.below(16) {
+                0 => b' ',
+                1 => b'\t',
+                _ => b'a' + rng.below(26) as u8,
+            });
+        }
+        data.push(b'\n');
+        if rng.below(blank_every) == 0 {
+            let blanks = 1 + rng.below(4) as usize;
+            data.resize(data.len() + blanks, b'\n');
+        }
+    }
+    data.truncate(size);
+    data
+}
+
+fn binary(size: usize, rng: &mut Rng) -> Vec<u8> {
+    (0..size).map(|_| rng.next() as u8).collect()
+}
+
+fn inputs(size: usize) -> Vec<(&'static str, Vec<u8>)> {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    vec![
+        ("short-lines", text(size, 12, 50, &mut rng)),
+        ("long-lines", text(size, 64 * 1024, 1000, &mut rng)),
+        ("binary", binary(size, &mut rng)),
+        ("blank-heavy", text(size, 8, 2, &mut rng)),
+    ]
+}
+
+/// The system's `name`, if it has one.
+fn system_tool(name: &str) -> Option<PathBuf> {
+    ["/usr/bin", "/bin"]
+        .iter()
+        .map(|dir| Path::new(dir).join(name))
+        .find(|path| path.is_file())
+}
+
+/// The shortest of `runs` timings of `f`.
+fn best<F: FnMut()>(runs: usize, mut f: F) -> Duration {
+    (0..runs)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn mb_per_s(bytes: usize, time: Duration) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0) / time.as_secs_f64()
+}
+
+/// Run `program` on `args` with its output thrown away.
+fn spawn(program: &Path, args: &[&str], input: Option<&Path>) {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if let Some(input) = input {
+        command.arg(input);
+    }
+    let status = command.status().expect("Failed to execute command");
+    assert!(status.success(), "{} {:?} failed", program.display(), args);
+}
+
+struct Bench {
+    filter: String,
+    runs: usize,
+}
+
+impl Bench {
+    fn wanted(&self, name: &str) -> bool {
+        name.contains(&self.filter)
+    }
+
+    fn cat(&self, size: usize, dir: &Path) {
+        let rtw_cat = PathBuf::from(env!("CARGO_BIN_EXE_cat"));
+        let system_cat = system_tool("cat");
+        println!(
+            "{:<36} {:>12} {:>12} {:>12}",
+            "cat (MB/s)",
+            "in-process",
+            "rtw cat",
+            system_cat.as_ref().map(|_| "system cat").unwrap_or("")
+        );
+        for (input_name, data) in inputs(size) {
+            let path = dir.join(input_name);
+            fs::write(&path, &data).unwrap();
+            for options in CAT_OPTIONS {
+                let name = format!("{} {}", input_name, options.join(" "));
+                if !self.wanted(&format!("cat {}", name)) {
+                    continue;
+                }
+                let in_process = best(self.runs, || {
+                    let mut cat = CatBuilder::new().build();
+                    cat.parse(*options).unwrap();
+                    cat.run(&mut &data[..], &mut io::sink(), &mut io::sink())
+                        .unwrap();
+                });
+                let binary = best(self.runs, || spawn(&rtw_cat, options, Some(&path)));
+                let system = system_cat
+                    .as_ref()
+                    .map(|cat| best(self.runs, || spawn(cat, options, Some(&path))));
+                println!(
+                    "{:<36} {:>12.1} {:>12.1} {:>12}",
+                    name,
+                    mb_per_s(data.len(), in_process),
+                    mb_per_s(data.len(), binary),
+                    system
+                        .map(|time| format!("{:.1}", mb_per_s(data.len(), time)))
+                        .unwrap_or_default()
+                );
+            }
+            fs::remove_file(&path).unwrap();
+        }
+        println!();
+    }
+
+    /// Invocations per second of `name` with each argument list in
+    /// `cases`: in-process, as rtw's binary and as the system's.
+    fn invocations(&self, name: &str, new: fn() -> Box<dyn Utility>, cases: &[&[&str]], bin: &str) {
+        let system = system_tool(name);
+        println!(
+            "{:<36} {:>12} {:>12} {:>12}",
+            format!("{} (calls/s)", name),
+            "in-process",
+            format!("rtw {}", name),
+            system
+                .as_ref()
+                .map(|_| format!("system {}", name))
+                .unwrap_or_default()
+        );
+        const CALLS: u32 = 10_000;
+        const SPAWNS: u32 = 200;
+        for args in cases {
+            let label = args.join(" ");
+            if !self.wanted(&format!("{} {}", name, label)) {
+                continue;
+            }
+            let os_args: Vec<OsString> = args.iter().map(OsString::from).collect();
+            let in_process = best(self.runs, || {
+                for _ in 0..CALLS {
+                    let mut stdin: &[u8] = b"";
+                    let mut out = io::sink();
+                    let mut err = io::sink();
+                    let mut ctx = Context::new(&mut stdin, &mut out, &mut err);
+                    new().call(&os_args, &mut ctx);
+                }
+            });
+            let rate = |program: &Path| {
+                let time = best(self.runs, || {
+                    for _ in 0..SPAWNS {
+                        spawn(program, args, None);
+                    }
+                });
+                SPAWNS as f64 / time.as_secs_f64()
+            };
+            println!(
+                "{:<36} {:>12.0} {:>12.0} {:>12}",
+                label,
+                CALLS as f64 / in_process.as_secs_f64(),
+                rate(Path::new(bin)),
+                system
+                    .as_ref()
+                    .map(|system| format!("{:.0}", rate(system)))
+                    .unwrap_or_default()
+            );
+        }
+        println!();
+    }
+}
+
+fn main() {
+    let var = |name: &str, default: usize| {
+        env::var(name)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
+    };
+    let bench = Bench {
+        // cargo passes --bench; anything else is a filter
+        filter: env::args()
+            .skip(1)
+            .find(|arg| !arg.starts_with("--"))
+            .unwrap_or_default(),
+        runs: var("RTW_BENCH_RUNS", 3).max(1),
+    };
+    let size = var("RTW_BENCH_MB", 16) * 1024 * 1024;
+
+    let dir = env::temp_dir().join(format!("rtw-bench-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    bench.cat(size, &dir);
+    fs::remove_dir_all(&dir).unwrap();
+
+    bench.invocations(
+        "echo",
+        || Box::new(EchoBuilder::new().build()),
+        &[&["hello", "world"], &["-ne", "a\\tb\\x41\\0101"]],
+        env!("CARGO_BIN_EXE_echo"),
+    );
+    bench.invocations(
+        "printf",
+        || Box::new(PrintfBuilder::new().build()),
+        &[
+            &["%s\\n", "hello"],
+            &["%5d|%-8.3f|%x\\n", "42", "3.14159", "255"],
+            &["%s %q\\n", "a", "b c", "d", "e'f"],
+        ],
+        env!("CARGO_BIN_EXE_printf"),
+    );
+}
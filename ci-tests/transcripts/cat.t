@@ -0,0 +1,102 @@
+cat numbers, marks and squeezes lines the way GNU cat does.
+
+  $ rtw cat -n cat_test.txt
+       1\tabcedfg (esc)
+       2\ttail4space     (esc)
+       3\t\t1tab (esc)
+       4\t\t\t2tab\t1tab\t\t\t3tab (esc)
+       5\t    4space\t  \t1tab2space1tab (esc)
+       6\t (esc)
+       7\t1blankline (esc)
+       8\t (esc)
+       9\t (esc)
+      10\t2blankline (esc)
+      11\t (esc)
+      12\t (esc)
+      13\t (esc)
+      14\t3blankline (esc)
+      15\t (esc)
+      16\t<nonprinting> (esc)
+      17\tCtrl-A: \x01 (esc)
+      18\tCtrl-C: \x03 (esc)
+      19\tCtrl-D: \x04 (esc)
+      20\tCtrl-E: \x05 (esc)
+      21\tCtrl-M: \r (esc)
+      22\tCtrl-R: \x12 (esc)
+      23\tCtrl-W: \x17 (esc)
+      24\tCursor <: \x1bOD (esc)
+      25\tCursor >: \x1bOC (esc)
+      26\tCursor ^: \x1bOA (esc)
+      27\tCursor v: \x1bOB (esc)
+      28\tPgUp: \x1b[5~ (esc)
+      29\tPgDn: \x1b[6~ (esc)
+      30\tHome: \x1b[1~ (esc)
+      31\tEnd:  \x1b[4~ (esc)
+      32\t (esc)
+  $ cat -A cat_test.txt
+  abcedfg$
+  tail4space    $
+  ^I1tab$
+  ^I^I2tab^I1tab^I^I^I3tab$
+      4space^I  ^I1tab2space1tab$
+  $
+  1blankline$
+  $
+  $
+  2blankline$
+  $
+  $
+  $
+  3blankline$
+  $
+  <nonprinting>$
+  Ctrl-A: ^A$
+  Ctrl-C: ^C$
+  Ctrl-D: ^D$
+  Ctrl-E: ^E$
+  Ctrl-M: ^M$
+  Ctrl-R: ^R$
+  Ctrl-W: ^W$
+  Cursor <: ^[OD$
+  Cursor >: ^[OC$
+  Cursor ^: ^[OA$
+  Cursor v: ^[OB$
+  PgUp: ^[[5~$
+  PgDn: ^[[6~$
+  Home: ^[[1~$
+  End:  ^[[4~$
+  $
+  $ printf 'a\n\n\n\nb\n' | cat -sb
+       1\ta (esc)
+  
+       2\tb (esc)
+  $ printf 'no newline' | cat -E
+  no newline (no-eol)
+
+Options may come after operands, unless `--` ends them.
+
+  $ cat cat_test.txt -n | head -n 2
+       1\tabcedfg (esc)
+       2\ttail4space     (esc)
+  $ cat -- -n
+  cat: -n: No such file or directory
+  [1]
+
+A missing operand is reported and the rest are still read.
+
+  $ cat missing.txt - <<EOF
+  > from stdin
+  > EOF
+  cat: missing.txt: No such file or directory
+  from stdin
+  [1]
+  $ cat -x cat_test.txt
+  cat: invalid option -- 'x'
+  Try 'cat --help' for more information.
+  [1]
+
+A file is not read into itself while standard output appends to it.
+
+  $ cp cat_test.txt log && cat log >> log
+  cat: log: input file is output file
+  [1]
@@ -0,0 +1,15 @@
+echo takes only leading -n, -e and -E as options. (The shell's own echo
+would shadow the applet, so it is run through rtw.)
+
+  $ rtw echo hello world
+  hello world
+  $ rtw echo -ne 'a\tb'
+  a\tb (esc) (no-eol)
+  $ rtw echo -e 'x\cy' z
+  x (no-eol)
+  $ rtw echo -nx y
+  -nx y
+  $ rtw echo -- -n
+  -- -n
+  $ POSIXLY_CORRECT=1 rtw echo -e 'a\tb'
+  -e a\tb (esc)
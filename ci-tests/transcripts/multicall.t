@@ -0,0 +1,13 @@
+rtw runs the applet it is named after, or the one named by its first
+argument.
+
+  $ rtw --list
+  bsdcat
+  cat
+  echo
+  printf
+  $ rtw echo via rtw
+  via rtw
+  $ rtw dog
+  rtw: applet not found: dog
+  [127]
@@ -0,0 +1,19 @@
+printf reuses its format while arguments remain. (The shell's own printf
+would shadow the applet, so it is run through rtw.)
+
+  $ rtw printf '%5d|%-5s|%x\n' 42 ab 255 7 c 16
+     42|ab   |ff
+      7|c    |10
+  $ rtw printf '%b %q\n' 'a\tb' "it's"
+  a\tb "it's" (esc)
+  $ rtw printf '%d\n' 12abc
+  12
+  printf: '12abc': value not completely converted
+  [1]
+  $ rtw printf '%z'
+  printf: %z: invalid conversion specification
+  [1]
+  $ rtw printf
+  printf: missing operand
+  Try 'printf --help' for more information.
+  [1]
@@ -1,65 +1,14 @@
+extern crate rtw;
+use rtw::echo;
 use std::env;
-
-fn unescape(s: &[String]) {
-    for (i, v) in s.iter().enumerate() {
-        if i > 0 {
-            print!(" ");
-        }
-        let mut backslashed = false;
-        for c in v.chars() {
-            if c == '\\' && !backslashed {
-                backslashed = true;
-                continue;
-            }
-            if backslashed {
-                match c {
-                    'n' => print!("\n"),
-                    'r' => print!("\r"),
-                    't' => print!("\t"),
-                    _ => print!("\\{}", c),
-                }
-                backslashed = false;
-            } else {
-                print!("{}", c);
-            }
-        }
-        if backslashed {
-            print!("\\");
-        }
-    }
-}
-
-fn raw(s: &[String]) {
-    for (i, v) in s.iter().enumerate() {
-        print!("{}{}", if i == 0 { "" } else { " " }, v);
-    }
-}
+use std::ffi::OsString;
+use std::process;
 
 fn main() {
-    let mut output_trailing_newline = true;
-    let mut enable_interp_backslash_escapes = true;
-
-    let args: Vec<String> = env::args().skip(1).collect();
-    let mut s = args.as_slice();
-
-    for (i, v) in s.iter().enumerate() {
-        match v.as_str() {
-            "-e" => enable_interp_backslash_escapes = true,
-            "-E" => enable_interp_backslash_escapes = false,
-            "-n" => output_trailing_newline = false,
-            _ => {
-                s = &s[i..];
-                break;
-            }
-        }
-    }
-
-    if enable_interp_backslash_escapes {
-        unescape(&s);
-    } else {
-        raw(&s);
-    }
-    if output_trailing_newline {
-        println!();
+    let args: Vec<OsString> = env::args_os().skip(1).collect();
+    let mut echo = echo::EchoBuilder::new().build();
+    let status = rtw::run_std(&mut echo, &args);
+    if status != 0 {
+        process::exit(status);
     }
 }
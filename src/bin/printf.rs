@@ -0,0 +1,14 @@
+extern crate rtw;
+use rtw::printf;
+use std::env;
+use std::ffi::OsString;
+use std::process;
+
+fn main() {
+    let args: Vec<OsString> = env::args_os().skip(1).collect();
+    let mut printf = printf::PrintfBuilder::new().build();
+    let status = rtw::run_std(&mut printf, &args);
+    if status != 0 {
+        process::exit(status);
+    }
+}
@@ -0,0 +1,23 @@
+extern crate rtw;
+use rtw::multicall::{self, Multicall};
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process;
+
+fn main() {
+    let mut args = env::args_os();
+    let argv0 = args.next().unwrap_or_default();
+    let args: Vec<OsString> = args.collect();
+    // run through a link named after an applet, be that applet
+    let status = match multicall::find(Path::new(&argv0)) {
+        Some(applet) => rtw::run_std(&mut *(applet.new)(), &args),
+        None => {
+            let exe = env::current_exe().unwrap_or_else(|_| PathBuf::from(&argv0));
+            rtw::run_std(&mut Multicall::new(&exe), &args)
+        }
+    };
+    if status != 0 {
+        process::exit(status);
+    }
+}
@@ -1,8 +1,151 @@
 #![allow(dead_code)]
 use super::cliopt;
+pub use super::color::{ColorWhen, Palette};
+use super::decompress;
+use super::follow;
+use super::glob;
+pub use super::input::Input;
+use super::json;
+pub use super::state::CatState;
+pub use super::timestamp::TimestampMode;
+use super::timestamp::Timestamper;
+use super::utility::{Context, Utility};
+use super::vfs::{FileKind, FileSystem, RealFs};
 use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io;
 use std::io::prelude::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// When the output stream is flushed while catting.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum Buffering {
+    /// flush only when the writer decides to (fast path for files and pipes)
+    #[default]
+    Full,
+    /// flush after every chunk that completes an output line
+    Line,
+    /// flush after every chunk read from the input
+    Unbuffered,
+}
+
+/// How directory operands are handled.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum Recursion {
+    /// directories are an error, like GNU cat
+    #[default]
+    Off,
+    /// descend into directories, skipping symbolic links found on the way
+    NoFollow,
+    /// descend into directories, following every symbolic link
+    Follow,
+}
+
+/// An inclusive range of 1-based line or byte positions, as given by
+/// `--lines=START:END` or `--bytes=START:END`. Either end may be omitted.
+///
+/// With `-n` or `-b`, the selected lines keep the numbers they have in the
+/// whole file: `cat -n --lines=S:E FILE` prints what `cat -n FILE | sed -n
+/// 'S,Ep'` does. Lines before the range are counted as `-n` or `-b` would
+/// number them; with `-s`, blank lines squeezed out of the selection are
+/// not.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Range {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Range {
+    pub fn new(start: u64, end: u64) -> Range {
+        Range { start, end }
+    }
+
+    fn parse(spec: &str) -> Option<Range> {
+        let (start, end) = spec.split_once(':')?;
+        let start = if start.is_empty() {
+            1
+        } else {
+            start.parse().ok()?
+        };
+        let end = if end.is_empty() {
+            u64::MAX
+        } else {
+            end.parse().ok()?
+        };
+        if start == 0 || end < start {
+            return None;
+        }
+        Some(Range { start, end })
+    }
+
+    fn contains(&self, pos: u64) -> bool {
+        self.start <= pos && pos <= self.end
+    }
+}
+
+/// Whose cat to behave like: the options it takes and how it lays out
+/// its output.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum Profile {
+    /// GNU coreutils cat, with all of the extensions here
+    #[default]
+    Gnu,
+    /// FreeBSD/macOS cat: only `-belnstuv`, option parsing stops at the
+    /// first operand, and each file is numbered and squeezed on its own
+    Bsd,
+}
+
+/// What `--binary` does with input that looks binary.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BinaryAction {
+    /// report an error and skip the file
+    Refuse,
+    /// render the file as with `-v` when writing to a terminal
+    Show,
+}
+
+/// How `--stats` reports are written.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StatsFormat {
+    Table,
+    Json,
+}
+
+/// What `--stats` counts for each input file.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Stats {
+    pub lines: u64,
+    pub bytes: u64,
+    /// blank lines removed by -s
+    pub squeezed: u64,
+    /// bytes other than printable ASCII, TAB and LFD
+    pub nonprinting: u64,
+    /// lines not terminated by a newline (at most one per file)
+    pub unterminated: u64,
+}
+
+impl Stats {
+    fn count(&mut self, chunk: &[u8]) {
+        self.bytes += chunk.len() as u64;
+        for &b in chunk {
+            match b {
+                b'\n' => self.lines += 1,
+                b'\t' | 32..=126 => (),
+                _ => self.nonprinting += 1,
+            }
+        }
+    }
+
+    fn add(&mut self, other: &Stats) {
+        self.lines += other.lines;
+        self.bytes += other.bytes;
+        self.squeezed += other.squeezed;
+        self.nonprinting += other.nonprinting;
+        self.unterminated += other.unterminated;
+    }
+}
 
 #[derive(Clone, Default, PartialEq, Debug)]
 struct CmdOption {
@@ -12,6 +155,26 @@ struct CmdOption {
     squeeze_blank: bool,
     show_tabs: bool,
     show_nonprinting: bool,
+    buffering: Buffering,
+    recursion: Recursion,
+    expand_globs: bool,
+    print_headers: bool,
+    number_per_file: bool,
+    decompress: bool,
+    lines: Option<Range>,
+    bytes: Option<Range>,
+    json: bool,
+    html: bool,
+    stats: Option<StatsFormat>,
+    timestamp: Option<TimestampMode>,
+    timestamp_format: Option<String>,
+    state_file: Option<PathBuf>,
+    follow: bool,
+    profile: Profile,
+    lock_output: bool,
+    binary: Option<BinaryAction>,
+    force: bool,
+    color: ColorWhen,
     display_help: bool,
     display_version: bool,
 }
@@ -24,6 +187,51 @@ pub struct Cat {
     option: CmdOption,
     number_count: u32,
     blank_count: u32,
+    at_line_start: bool,
+    pending_cr: bool,
+    files_started: u32,
+    /// 1-based line and byte position in the current input file
+    file_line: u64,
+    file_byte: u64,
+    file_name: PathBuf,
+    /// bytes read from the current input file, and from each finished one
+    file_offset: u64,
+    offsets: Vec<(PathBuf, u64)>,
+    /// offsets restored by `set_state`, each resumed from once
+    resume_offsets: Vec<(PathBuf, u64)>,
+    /// the line being collected for `--json`
+    record: Option<Record>,
+    html_started: bool,
+    stats: Stats,
+    file_stats: Vec<(PathBuf, Stats)>,
+    timestamper: Option<Timestamper>,
+    started: SystemTime,
+    clock: fn() -> SystemTime,
+    terminal_output: bool,
+    /// `NO_COLOR` is set, which turns off `--color=auto`
+    no_color: bool,
+    palette: Palette,
+    /// FILE operands given to `parse_args`
+    operands: Vec<PathBuf>,
+    /// some input was refused, though catting went on
+    failed: bool,
+    /// saving the `--state` file failed, which has been reported
+    state_failed: bool,
+}
+
+/// One input line collected for `--json` output.
+struct Record {
+    line: u64,
+    offset: u64,
+    /// whether the line began inside the selected range
+    whole: bool,
+    text: Vec<u8>,
+}
+
+impl Default for CatBuilder {
+    fn default() -> CatBuilder {
+        CatBuilder::new()
+    }
 }
 
 impl CatBuilder {
@@ -72,6 +280,86 @@ impl CatBuilder {
         self.option.show_nonprinting = true;
         self
     }
+    pub fn with_unbuffered(&mut self) -> &mut Self {
+        self.option.buffering = Buffering::Unbuffered;
+        self
+    }
+    pub fn with_line_buffered(&mut self) -> &mut Self {
+        self.option.buffering = Buffering::Line;
+        self
+    }
+    pub fn with_recursive(&mut self) -> &mut Self {
+        self.option.recursion = Recursion::NoFollow;
+        self
+    }
+    pub fn with_dereference_recursive(&mut self) -> &mut Self {
+        self.option.recursion = Recursion::Follow;
+        self
+    }
+    pub fn with_glob(&mut self) -> &mut Self {
+        self.option.expand_globs = true;
+        self
+    }
+    pub fn with_headers(&mut self) -> &mut Self {
+        self.option.print_headers = true;
+        self
+    }
+    pub fn with_number_per_file(&mut self) -> &mut Self {
+        self.option.number_per_file = true;
+        self
+    }
+    pub fn with_profile(&mut self, profile: Profile) -> &mut Self {
+        self.option.profile = profile;
+        self
+    }
+    pub fn with_lock_output(&mut self) -> &mut Self {
+        self.option.lock_output = true;
+        self
+    }
+    pub fn with_binary(&mut self, action: BinaryAction) -> &mut Self {
+        self.option.binary = Some(action);
+        self
+    }
+    pub fn with_color(&mut self, when: ColorWhen) -> &mut Self {
+        self.option.color = when;
+        self
+    }
+    pub fn with_force(&mut self) -> &mut Self {
+        self.option.force = true;
+        self
+    }
+    pub fn with_decompress(&mut self) -> &mut Self {
+        self.option.decompress = true;
+        self
+    }
+    pub fn with_lines(&mut self, range: Range) -> &mut Self {
+        self.option.lines = Some(range);
+        self
+    }
+    pub fn with_bytes(&mut self, range: Range) -> &mut Self {
+        self.option.bytes = Some(range);
+        self
+    }
+    pub fn with_json(&mut self) -> &mut Self {
+        self.option.json = true;
+        self
+    }
+    pub fn with_html(&mut self) -> &mut Self {
+        self.option.html = true;
+        self
+    }
+    pub fn with_stats(&mut self, format: StatsFormat) -> &mut Self {
+        self.option.stats = Some(format);
+        self
+    }
+    pub fn with_timestamp(&mut self, mode: TimestampMode) -> &mut Self {
+        self.option.timestamp = Some(mode);
+        self
+    }
+    pub fn with_timestamp_format(&mut self, format: &str) -> &mut Self {
+        self.option.timestamp_format = Some(format.to_string());
+        self
+    }
     pub fn with_display_help(&mut self) -> &mut Self {
         if !self.option.display_version {
             self.option.display_help = true;
@@ -89,14 +377,129 @@ impl CatBuilder {
             option: self.option.clone(),
             number_count: 1,
             blank_count: 0,
+            at_line_start: true,
+            pending_cr: false,
+            files_started: 0,
+            file_line: 1,
+            file_byte: 1,
+            file_name: PathBuf::from("-"),
+            file_offset: 0,
+            offsets: Vec::new(),
+            resume_offsets: Vec::new(),
+            record: None,
+            html_started: false,
+            stats: Stats::default(),
+            file_stats: Vec::new(),
+            timestamper: None,
+            started: SystemTime::now(),
+            clock: SystemTime::now,
+            terminal_output: false,
+            no_color: false,
+            palette: Palette::default(),
+            operands: Vec::new(),
+            failed: false,
+            state_failed: false,
+        }
+    }
+
+    /// Build a `Cat` that continues from a saved `state`, as if the run that
+    /// produced it had never stopped.
+    pub fn resume(&self, state: &CatState) -> Cat {
+        let mut cat = self.build();
+        cat.set_state(state);
+        cat
+    }
+}
+
+const HTML_STYLE: &[u8] = b"<style>\n\
+pre.cat .np { color: #b00; font-weight: bold; }\n\
+pre.cat .tab, pre.cat .eol { color: #888; }\n\
+pre.cat .ts { color: #06c; }\n\
+pre.cat .ln { color: #888; text-decoration: none; user-select: none; }\n\
+pre.cat .line:target { background: #ffc; }\n\
+div.cat-file { font-family: monospace; font-weight: bold; }\n\
+</style>\n";
+
+/// Describe an I/O error the way the C library does, without the
+/// "(os error N)" suffix Rust adds.
+pub fn describe(err: &io::Error) -> String {
+    let text = err.to_string();
+    match text.find(" (os error") {
+        Some(pos) => text[..pos].to_string(),
+        None => text,
+    }
+}
+
+/// Report that writing standard output failed with `err`.
+fn write_error<E: Write + ?Sized>(err_stream: &mut E, err: &io::Error) {
+    writeln!(err_stream, "cat: write error: {}", describe(err)).unwrap();
+}
+
+/// Take an exclusive lock on standard output, waiting for other holders,
+/// as BSD `cat -l` does so that concurrent writers to one file do not
+/// interleave their output.
+#[cfg(unix)]
+pub fn lock_stdout() -> io::Result<()> {
+    // SAFETY: fcntl only reads the flock we own
+    unsafe {
+        let mut lock: libc::flock = std::mem::zeroed();
+        lock.l_type = libc::F_WRLCK as libc::c_short;
+        lock.l_whence = libc::SEEK_SET as libc::c_short;
+        if libc::fcntl(libc::STDOUT_FILENO, libc::F_SETLKW, &lock) == -1 {
+            return Err(io::Error::last_os_error());
         }
     }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn lock_stdout() -> io::Result<()> {
+    Ok(())
+}
+
+/// Guess from the first block of an input whether it is binary: it holds
+/// a NUL byte, or more than 30% of it is neither printable text (ASCII or
+/// UTF-8) nor common whitespace.
+fn looks_binary(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return true;
+    }
+    let mut odd = 0;
+    let mut chunks = head.utf8_chunks().peekable();
+    while let Some(chunk) = chunks.next() {
+        odd += chunk
+            .valid()
+            .bytes()
+            .filter(|&b| b < 32 && !matches!(b, b'\t' | b'\n' | b'\r' | b'\x0c' | 0x1b))
+            .count();
+        // a few bytes at the very end may be a character cut off by the block
+        if chunks.peek().is_some() || chunk.invalid().len() >= 4 {
+            odd += chunk.invalid().len();
+        }
+    }
+    odd * 10 > head.len() * 3
+}
+
+/// Append `b` to `out` using ^ and M- notation, except for LFD and TAB.
+fn push_nonprinting(out: &mut Vec<u8>, b: u8) {
+    match b {
+        // ascii control characters
+        b'\t' | b'\n' => out.push(b),
+        0..=31 => out.extend_from_slice(&[b'^', b + 64]),
+        // ascii graphic characters
+        32..=126 => out.push(b),
+        127 => out.extend_from_slice(b"^?"),
+        // 128 + 0 .. 128 + 31
+        128..=159 => out.extend_from_slice(&[b'M', b'-', b'^', b - 128 + 64]),
+        // 128 + 32 .. 128 + 126
+        160..=254 => out.extend_from_slice(&[b'M', b'-', b - 128]),
+        255 => out.extend_from_slice(b"M-^?"),
+    }
 }
 
 impl Cat {
     fn parse_option(&mut self, opt: &OsStr) -> Result<(), String> {
-        // let opt_ref = opt.to_str().unwrap();
-        match opt.to_str().unwrap() {
+        match &*opt.to_string_lossy() {
             "-A" | "--show-all" => {
                 // equivalent to -vET
                 self.option.show_nonprinting = true;
@@ -133,11 +536,126 @@ impl Cat {
                 // display TAB characters as ^I
                 self.option.show_tabs = true;
             }
-            "-u" => (), // (ignored)
+            "-u" | "--unbuffered" => {
+                // write out everything as soon as it is read
+                self.option.buffering = Buffering::Unbuffered;
+            }
+            "--line-buffered" => {
+                // flush output at the end of every line
+                self.option.buffering = Buffering::Line;
+            }
+            "-r" | "--recursive" => {
+                // read all files under each directory, skipping symlinks inside them
+                self.option.recursion = Recursion::NoFollow;
+            }
+            "-R" | "--dereference-recursive" => {
+                // likewise, but follow all symbolic links
+                self.option.recursion = Recursion::Follow;
+            }
+            "--glob" => {
+                // expand wildcards in FILE operands
+                self.option.expand_globs = true;
+            }
+            "-H" | "--headers" => {
+                // print a '==> FILE <==' header before each file
+                self.option.print_headers = true;
+            }
+            "--number-per-file" => {
+                // restart line numbers for each file
+                self.option.number_per_file = true;
+            }
+            "-z" | "--decompress" => {
+                // decompress gzip (and optionally xz, zstd, bzip2) input
+                self.option.decompress = true;
+            }
             "-v" | "--show-nonprinting" => {
                 // "use ^ and M- notation, except for LFD and TAB
                 self.option.show_nonprinting = true;
             }
+            "--json" => {
+                // print each line as a JSON object (JSON Lines)
+                self.option.json = true;
+            }
+            "--html" => {
+                // render the output as HTML, with styled markers and line anchors
+                self.option.html = true;
+            }
+            "--stats" | "--stats=table" => {
+                // report per-file statistics on standard error
+                self.option.stats = Some(StatsFormat::Table);
+            }
+            "--stats=json" => {
+                // likewise, as a JSON object
+                self.option.stats = Some(StatsFormat::Json);
+            }
+            "--timestamp" | "--timestamp=absolute" => {
+                // prefix each line with the time it was read
+                self.option.timestamp = Some(TimestampMode::Absolute);
+            }
+            "--timestamp=relative" => {
+                // prefix each line with the time since start
+                self.option.timestamp = Some(TimestampMode::Relative);
+            }
+            "--timestamp=delta" => {
+                // prefix each line with the time since the previous line
+                self.option.timestamp = Some(TimestampMode::Delta);
+            }
+            o if o.starts_with("--timestamp-format=") => {
+                // strftime-like FORMAT for --timestamp
+                self.option.timestamp_format = Some(o["--timestamp-format=".len()..].to_string());
+            }
+            "--binary=refuse" => {
+                // refuse input that looks binary
+                self.option.binary = Some(BinaryAction::Refuse);
+            }
+            "--binary=show" => {
+                // show input that looks binary as with -v on terminals
+                self.option.binary = Some(BinaryAction::Show);
+            }
+            "--color" | "--color=always" => {
+                // highlight markers and line numbers with ANSI colours
+                self.option.color = ColorWhen::Always;
+            }
+            "--color=auto" => {
+                // likewise, when writing to a terminal
+                self.option.color = ColorWhen::Auto;
+            }
+            "--color=never" => {
+                // never highlight
+                self.option.color = ColorWhen::Never;
+            }
+            "--force" => {
+                // cat binary input as it is despite --binary
+                self.option.force = true;
+            }
+            "--follow" => {
+                // keep reading the last FILE as it grows, like 'tail -F'
+                self.option.follow = true;
+            }
+            o if o.starts_with("--state=") => {
+                // resume from and save the running state in FILE
+                self.option.state_file = Some(PathBuf::from(&o["--state=".len()..]));
+            }
+            o if o.starts_with("--lines=") || o.starts_with("--bytes=") => {
+                // output only lines (or bytes) START through END of each file
+                let (name, spec) = o.split_at(o.find('=').unwrap());
+                let range = match Range::parse(&spec[1..]) {
+                    Some(range) => range,
+                    None => {
+                        return Err(format!(
+                            "cat: invalid range '{}' for '{}'\n\
+                             Try 'cat --help' for more information.",
+                            &spec[1..],
+                            name
+                        ))
+                    }
+                };
+                if name == "--lines" {
+                    self.option.lines = Some(range);
+                } else {
+                    self.option.bytes = Some(range);
+                }
+            }
             "--help" => {
                 // display this help and exit
                 if !self.option.display_version {
@@ -155,13 +673,13 @@ impl Cat {
                     format!(
                         "cat: invalid option -- '{}'\n\
                          Try 'cat --help' for more information.",
-                        opt.to_str().unwrap().get(1..).unwrap()
+                        &opt.to_string_lossy()[1..]
                     )
                 } else {
                     format!(
                         "cat: unrecognized option '{}'\n\
                          Try 'cat --help' for more information.",
-                        opt.to_str().unwrap()
+                        opt.to_string_lossy()
                     )
                 })
             }
@@ -169,190 +687,1494 @@ impl Cat {
         Ok(())
     }
 
+    /// Parse command line arguments, returning the FILE operands.
+    /// `--profile=gnu|bsd` anywhere before `--` selects how the rest is read.
+    /// As with GNU getopt, short options may be clustered, options and
+    /// operands may be mixed, and everything after `--` is an operand.
     pub fn parse<I, S>(&mut self, args: I) -> Result<Vec<PathBuf>, String>
     where
         I: IntoIterator<Item = S>,
         S: AsRef<OsStr>,
     {
+        let mut rest: Vec<&OsStr> = Vec::new();
+        let args: Vec<S> = args.into_iter().collect();
+        let mut iter = args.iter().map(|arg| arg.as_ref());
+        for arg in iter.by_ref() {
+            match arg.to_str() {
+                Some("--profile=gnu") => self.option.profile = Profile::Gnu,
+                Some("--profile=bsd") => self.option.profile = Profile::Bsd,
+                Some(o) if o.starts_with("--profile=") => {
+                    return Err(format!(
+                        "cat: invalid argument '{}' for '--profile'\n\
+                         Valid arguments are:\n  - 'gnu'\n  - 'bsd'\n\
+                         Try 'cat --help' for more information.",
+                        &o["--profile=".len()..]
+                    ));
+                }
+                Some("--") => {
+                    rest.push(arg);
+                    break;
+                }
+                _ => rest.push(arg),
+            }
+        }
+        rest.extend(iter);
+        if self.option.profile == Profile::Bsd {
+            return self.parse_bsd(&rest);
+        }
+
         let mut files: Vec<PathBuf> = Vec::new();
-        for arg in args {
-            if cliopt::is_option(arg.as_ref()) {
-                self.parse_option(arg.as_ref())?;
+        let mut rest = rest.into_iter();
+        for arg in rest.by_ref() {
+            if arg == "--" {
+                break;
+            } else if cliopt::is_singlechar_option(arg) && arg.len() > 2 {
+                // clustered short options, as in -nE
+                for c in arg.to_string_lossy()[1..].chars() {
+                    self.parse_option(OsStr::new(&format!("-{}", c)))?;
+                }
+            } else if cliopt::is_option(arg) {
+                self.parse_option(arg)?;
             } else {
-                files.push(PathBuf::from(arg.as_ref()));
+                files.push(PathBuf::from(arg));
             }
         }
+        files.extend(rest.map(PathBuf::from));
+        if self.option.decompress && self.option.state_file.is_some() {
+            // the saved offsets would count decompressed bytes, which
+            // cannot be sought to in the compressed file
+            return Err("cat: --state cannot be used with --decompress\n\
+                        Try 'cat --help' for more information."
+                .to_string());
+        }
+        if self.option.decompress && self.option.follow {
+            // appended data, truncation and rotation are told apart by
+            // offsets into the compressed file
+            return Err("cat: --follow cannot be used with --decompress\n\
+                        Try 'cat --help' for more information."
+                .to_string());
+        }
         Ok(files)
     }
 
-    fn help<W: Write>(&self, out_stream: &mut W) {
-        writeln!(out_stream, "help contents is here.").unwrap();
-    }
-
-    fn version<W: Write>(&self, out_stream: &mut W) {
-        writeln!(out_stream, "rust cat version 0.1.0").unwrap();
-    }
-
-    fn cat<R, W, E>(
-        &mut self,
-        in_stream: &mut R,
-        out_stream: &mut W,
-        err_stream: &mut E,
-    ) -> Result<(), ()>
-    where
-        R: BufRead + ?Sized,
-        W: Write,
-        E: Write,
-    {
-        if self.option.number_noblank {
-            // number nonempty output lines, overrides -n
-            self.option.number = false;
-        }
-
-        let mut contents = String::new();
-        loop {
-            match in_stream.read_line(&mut contents) {
-                Ok(n) => {
-                    if n == 0 {
-                        break;
-                    }
-                    let newline_pat: &[_] = &['\n'];
-                    contents = format!("{}", contents.trim_right_matches(newline_pat));
-                    if self.option.squeeze_blank {
-                        if contents.len() == 0 {
-                            self.blank_count += 1;
-                            if self.blank_count > 1 {
-                                continue;
-                            }
-                        } else {
-                            self.blank_count = 0;
-                        }
-                    }
-                    if self.option.show_nonprinting {
-                        let mut rep_line = String::new();
-                        for b in contents.as_str().bytes() {
-                            let c = match b {
-                                // ascii control characters
-                                9 => "\t".to_string(),
-                                0...31 => format!("^{}", (b + 64) as char),
-                                // ascii graphic characters
-                                32...126 => format!("{}", (b) as char),
-                                127 => "^?".to_string(),
-                                // 128 + 32 .. 128 + 126
-                                160...254 => format!("M-{}", (b - 128) as char),
-                                v if v > 254 => "M-^?".to_string(),
-                                v => format!("^{}", (v - 128 + 64) as char),
-                            };
-                            rep_line += c.as_str();
-                        }
-                        contents = rep_line;
+    /// Parse arguments the way BSD cat's getopt("belnstuv") does: options
+    /// may be clustered and end at `--` or the first operand.
+    fn parse_bsd(&mut self, args: &[&OsStr]) -> Result<Vec<PathBuf>, String> {
+        let mut consumed = 0;
+        for arg in args {
+            let arg = match arg.to_str() {
+                Some("--") => {
+                    consumed += 1;
+                    break;
+                }
+                Some(arg) if arg.len() > 1 && arg.starts_with('-') => arg,
+                _ => break,
+            };
+            for c in arg[1..].chars() {
+                match c {
+                    // number nonblank lines, and show empty ones with -e
+                    'b' => {
+                        self.option.number = true;
+                        self.option.number_noblank = true;
                     }
-                    if self.option.show_tabs {
-                        contents = contents.replace("\t", "^I");
+                    // -v, and mark line ends with '$'
+                    'e' => {
+                        self.option.show_nonprinting = true;
+                        self.option.show_ends = true;
                     }
-                    if (self.option.number_noblank && contents.len() > 0) || self.option.number {
-                        contents = format!("{:>6}\t{}", self.number_count, contents);
-                        self.number_count += 1;
+                    // lock standard output while writing
+                    'l' => self.option.lock_output = true,
+                    // number output lines
+                    'n' => self.option.number = true,
+                    // squeeze repeated empty lines
+                    's' => self.option.squeeze_blank = true,
+                    // -v, and show TAB as ^I
+                    't' => {
+                        self.option.show_nonprinting = true;
+                        self.option.show_tabs = true;
                     }
-                    if self.option.show_ends {
-                        contents = format!("{}$", contents);
+                    // unbuffered output
+                    'u' => self.option.buffering = Buffering::Unbuffered,
+                    // show nonprinting characters visibly
+                    'v' => self.option.show_nonprinting = true,
+                    _ => {
+                        return Err(format!(
+                            "cat: illegal option -- {}\nusage: cat [-belnstuv] [file ...]",
+                            c
+                        ))
                     }
-                    writeln!(out_stream, "{}", contents).unwrap();
-                }
-                Err(err) => {
-                    writeln!(err_stream, "error occured! {:?}", err.kind()).unwrap();
-                    return Err(());
                 }
             }
-            contents.clear();
+            consumed += 1;
         }
-        Ok(())
+        Ok(args[consumed..].iter().map(PathBuf::from).collect())
     }
 
-    pub fn run<R, W, E>(
-        &mut self,
-        in_stream: &mut R,
-        out_stream: &mut W,
-        err_stream: &mut E,
-    ) -> Result<(), ()>
-    where
-        R: BufRead + ?Sized,
-        W: Write,
-        E: Write,
-    {
-        if self.option.display_version {
-            self.version(out_stream);
-            Err(())
-        } else if self.option.display_help {
-            self.help(out_stream);
-            Err(())
-        } else {
-            self.cat(in_stream, out_stream, err_stream)
-        }
+    /// Which cat's behaviour is followed; `--profile` overrides the default.
+    pub fn profile(&self) -> Profile {
+        self.option.profile
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs::File;
-    use std::io::{BufReader, SeekFrom};
-    use std::process;
+    /// Whether `-l` asked for standard output to be locked, which the
+    /// caller does with `lock_stdout` before writing.
+    pub fn locks_output(&self) -> bool {
+        self.option.lock_output
+    }
 
-    #[test]
-    fn test_build() {
-        let c = CatBuilder::new().build();
-        assert_eq!(CmdOption::default(), c.option);
+    /// How the output stream is flushed; `-u` and `--line-buffered` override the default.
+    pub fn buffering(&self) -> Buffering {
+        self.option.buffering
+    }
 
-        let c = CatBuilder::new().with_show_all().build();
-        assert_eq!(
-            CmdOption {
-                show_ends: true,
-                show_tabs: true,
-                show_nonprinting: true,
-                ..Default::default()
-            },
-            c.option
-        );
+    pub fn set_buffering(&mut self, buffering: Buffering) {
+        self.option.buffering = buffering;
+    }
 
-        let c = CatBuilder::new().with_number_nonblank().build();
-        assert_eq!(
-            CmdOption {
-                number_noblank: true,
-                ..Default::default()
-            },
-            c.option
-        );
+    /// Apply the environment to `--color`: `NO_COLOR`, when set and not
+    /// empty, turns off `auto`, and `RTW_CAT_COLORS` changes the palette
+    /// (see `Palette::parse`).
+    pub fn set_color_env(&mut self, no_color: Option<&OsStr>, palette: Option<&OsStr>) {
+        self.no_color = no_color.is_some_and(|v| !v.is_empty());
+        self.palette = match palette.and_then(|p| p.to_str()) {
+            Some(spec) => Palette::parse(spec),
+            None => Palette::default(),
+        };
+    }
 
-        let c = CatBuilder::new().with_e().build();
-        assert_eq!(
-            CmdOption {
-                show_ends: true,
-                show_nonprinting: true,
-                ..Default::default()
-            },
-            c.option
-        );
+    fn colored(&self) -> bool {
+        match self.option.color {
+            ColorWhen::Always => !self.option.html,
+            ColorWhen::Auto => self.terminal_output && !self.no_color && !self.option.html,
+            ColorWhen::Never => false,
+        }
+    }
 
-        let c = CatBuilder::new().with_show_ends().build();
-        assert_eq!(
-            CmdOption {
-                show_ends: true,
-                ..Default::default()
-            },
-            c.option
-        );
+    /// Tell whether the output goes to a terminal, which `--binary=show`
+    /// and `--color=auto` depend on.
+    pub fn set_terminal_output(&mut self, terminal: bool) {
+        self.terminal_output = terminal;
+    }
 
-        let c = CatBuilder::new().with_number().build();
-        assert_eq!(
-            CmdOption {
-                number: true,
-                ..Default::default()
-            },
-            c.option
-        );
+    /// Whether some input was refused without stopping the run, so that
+    /// the exit status should report failure.
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
 
-        let c = CatBuilder::new().with_squeeze_blank().build();
+    /// The file named by `--state=FILE`, which the caller loads with
+    /// `set_state` before the run and saves from `state` after it.
+    pub fn state_file(&self) -> Option<&Path> {
+        self.option.state_file.as_deref()
+    }
+
+    /// Whether `--follow` was given; the caller then uses `follow` for the
+    /// last file instead of `run`.
+    pub fn follows(&self) -> bool {
+        self.option.follow
+    }
+
+    /// A snapshot of the running state between input files.
+    pub fn state(&self) -> CatState {
+        CatState {
+            number: self.number_count,
+            blank: self.blank_count,
+            at_line_start: self.at_line_start,
+            offsets: self.offsets.clone(),
+        }
+    }
+
+    /// Continue from a snapshot taken by `state`.
+    pub fn set_state(&mut self, state: &CatState) {
+        self.number_count = state.number;
+        self.blank_count = state.blank;
+        self.at_line_start = state.at_line_start;
+        self.offsets = state.offsets.clone();
+        self.resume_offsets = state.offsets.clone();
+    }
+
+    /// Turn FILE operands into the list of files to read, expanding wildcards
+    /// and directories as requested. Operands that cannot be read are
+    /// returned in place as `Err` with a message ready to print.
+    pub fn expand_operands(&self, files: &[PathBuf]) -> Vec<Result<PathBuf, String>> {
+        self.expand_operands_in(&RealFs::DETACHED, files)
+    }
+
+    /// `expand_operands` with the operands looked up in `fs`. Wildcards
+    /// and recursion still read the real directories.
+    pub fn expand_operands_in(
+        &self,
+        fs: &dyn FileSystem,
+        files: &[PathBuf],
+    ) -> Vec<Result<PathBuf, String>> {
+        let mut expanded = Vec::new();
+        for file in files {
+            if file.as_os_str() == "-" {
+                expanded.push(Ok(file.clone()));
+                continue;
+            }
+            let mut candidates = Vec::new();
+            if self.option.expand_globs && glob::has_magic(file) {
+                candidates = glob::expand(file);
+            }
+            if candidates.is_empty() {
+                // like the shell, an unmatched pattern is kept as it is
+                candidates.push(file.clone());
+            }
+            for path in candidates {
+                let is_dir = fs
+                    .metadata(&path)
+                    .map(|info| info.kind == FileKind::Dir)
+                    .unwrap_or(false);
+                if !is_dir {
+                    expanded.push(Ok(path));
+                } else if self.option.recursion == Recursion::Off {
+                    expanded.push(Err(format!("cat: {}: Is a directory", path.display())));
+                } else {
+                    let mut ancestors = Vec::new();
+                    self.walk(&path, &mut ancestors, &mut expanded);
+                }
+            }
+        }
+        expanded
+    }
+
+    fn walk(
+        &self,
+        dir: &Path,
+        ancestors: &mut Vec<PathBuf>,
+        expanded: &mut Vec<Result<PathBuf, String>>,
+    ) {
+        let real = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        if ancestors.contains(&real) {
+            expanded.push(Err(format!(
+                "cat: {}: warning: recursive directory loop",
+                dir.display()
+            )));
+            return;
+        }
+        let mut entries: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+            Err(err) => {
+                expanded.push(Err(format!("cat: {}: {}", dir.display(), describe(&err))));
+                return;
+            }
+        };
+        entries.sort();
+        ancestors.push(real);
+        for path in entries {
+            let meta = match fs::symlink_metadata(&path) {
+                Ok(meta) => meta,
+                Err(err) => {
+                    expanded.push(Err(format!("cat: {}: {}", path.display(), describe(&err))));
+                    continue;
+                }
+            };
+            let meta = if meta.file_type().is_symlink() {
+                if self.option.recursion != Recursion::Follow {
+                    continue;
+                }
+                match fs::metadata(&path) {
+                    Ok(meta) => meta,
+                    Err(err) => {
+                        expanded.push(Err(format!("cat: {}: {}", path.display(), describe(&err))));
+                        continue;
+                    }
+                }
+            } else {
+                meta
+            };
+            if meta.is_dir() {
+                self.walk(&path, ancestors, expanded);
+            } else {
+                expanded.push(Ok(path));
+            }
+        }
+        ancestors.pop();
+    }
+
+    /// Prepare for the next input file: print its header and restart the
+    /// numbering when those options are set. Call before `run` for each file.
+    pub fn start_file<W: Write>(&mut self, path: &Path, out_stream: &mut W) -> io::Result<()> {
+        if self.option.number_per_file || self.option.profile == Profile::Bsd {
+            self.number_count = 1;
+        }
+        if self.option.profile == Profile::Bsd {
+            // BSD cat keeps no line state from one file to the next
+            self.at_line_start = true;
+            self.blank_count = 0;
+        }
+        self.file_name = path.to_path_buf();
+        if self.option.print_headers && self.option.html {
+            // written by begin_file, escaped and inside the document
+            self.at_line_start = true;
+            self.blank_count = 0;
+        } else if self.option.print_headers && !self.option.json {
+            if self.pending_cr {
+                self.pending_cr = false;
+                out_stream.write_all(b"\r")?;
+            }
+            if self.files_started > 0 {
+                out_stream.write_all(b"\n")?;
+            }
+            let name = if path.as_os_str() == "-" {
+                "standard input".to_string()
+            } else {
+                path.display().to_string()
+            };
+            writeln!(out_stream, "==> {} <==", name)?;
+            self.at_line_start = true;
+            self.blank_count = 0;
+        }
+        self.files_started += 1;
+        Ok(())
+    }
+
+    fn help<W: Write>(&self, out_stream: &mut W) {
+        writeln!(out_stream, "help contents is here.").unwrap();
+    }
+
+    fn version<W: Write>(&self, out_stream: &mut W) {
+        writeln!(out_stream, "rust cat version 0.1.0").unwrap();
+    }
+
+    /// Position `input` at the start of the `--bytes` range so the skipped
+    /// bytes are never read. Only done when nothing needs to see them, i.e.
+    /// without line numbering, `--lines` or decompression. Returns whether
+    /// the input was moved; call before `run`.
+    pub fn seek_input<S: Seek>(&mut self, input: &mut S) -> io::Result<bool> {
+        let start = match self.option.bytes {
+            Some(range) if range.start > 1 => range.start,
+            _ => return Ok(false),
+        };
+        if self.option.lines.is_some()
+            || self.option.number
+            || self.option.number_noblank
+            || self.option.decompress
+        {
+            return Ok(false);
+        }
+        input.seek(io::SeekFrom::Start(start - 1))?;
+        self.file_offset = start - 1;
+        self.file_byte = start;
+        Ok(true)
+    }
+
+    /// Position `input`, the file at `path`, where the run the state was
+    /// restored from stopped reading it. A file that is now shorter than
+    /// that was truncated or replaced and is read from the start. Returns
+    /// the offset read from; call before `start_file` and `run`. Only the
+    /// first time `path` is read resumes, so an operand given twice is read
+    /// in full the second time.
+    pub fn resume_input<S: Seek>(&mut self, path: &Path, input: &mut S) -> io::Result<u64> {
+        let offset = match self.resume_offsets.iter().position(|(p, _)| p == path) {
+            Some(index) => self.resume_offsets.remove(index).1,
+            None => return Ok(0),
+        };
+        if offset == 0 {
+            return Ok(0);
+        }
+        let offset = if input.seek(io::SeekFrom::End(0))? < offset {
+            0
+        } else {
+            offset
+        };
+        input.seek(io::SeekFrom::Start(offset))?;
+        self.file_offset = offset;
+        self.file_byte = offset + 1;
+        Ok(offset)
+    }
+
+    fn in_range(&self) -> bool {
+        self.option.lines.is_none_or(|r| r.contains(self.file_line))
+            && self.option.bytes.is_none_or(|r| r.contains(self.file_byte))
+    }
+
+    fn past_range(&self) -> bool {
+        self.option.lines.is_some_and(|r| self.file_line > r.end)
+            || self.option.bytes.is_some_and(|r| self.file_byte > r.end)
+    }
+
+    /// Transform the parts of `chunk` that fall in the `--lines`/`--bytes`
+    /// ranges into `out`. Returns how much of `chunk` was used and whether
+    /// the rest of the file can be left unread.
+    fn select(&mut self, chunk: &[u8], out: &mut Vec<u8>) -> (usize, bool) {
+        let mut used = 0;
+        while used < chunk.len() {
+            if self.past_range() {
+                return (used, true);
+            }
+            let rest = &chunk[used..];
+            // a segment never crosses a line end or a byte range boundary
+            let mut len = match rest.iter().position(|&b| b == b'\n') {
+                Some(pos) => pos + 1,
+                None => rest.len(),
+            };
+            if let Some(r) = self.option.bytes {
+                let boundary = if self.file_byte < r.start {
+                    r.start - self.file_byte
+                } else {
+                    r.end - self.file_byte + 1
+                };
+                len = len.min(boundary.min(usize::MAX as u64) as usize);
+            }
+            let segment = &rest[..len];
+            if self.in_range() {
+                self.transform(segment, out);
+            } else {
+                self.skip(segment);
+            }
+            self.file_byte += len as u64;
+            if segment.last() == Some(&b'\n') {
+                self.file_line += 1;
+            }
+            used += len;
+        }
+        (used, self.past_range())
+    }
+
+    /// Account for input that is not output, so that line numbers keep
+    /// counting the lines of the original file.
+    fn skip(&mut self, chunk: &[u8]) {
+        for &b in chunk {
+            if self.at_line_start && (b != b'\n' || !self.option.number_noblank) {
+                self.number_count += 1;
+            }
+            self.at_line_start = b == b'\n';
+        }
+    }
+
+    fn is_plain(&self) -> bool {
+        !(self.option.number
+            || self.option.number_noblank
+            || self.option.show_ends
+            || self.option.squeeze_blank
+            || self.option.show_tabs
+            || self.option.show_nonprinting
+            || self.option.html
+            || self.option.stats.is_some()
+            || self.option.timestamp.is_some())
+    }
+
+    /// Statistics of each input read so far, for `--stats`.
+    pub fn stats(&self) -> &[(PathBuf, Stats)] {
+        &self.file_stats
+    }
+
+    /// Write the `--stats` report of every input read so far. Does nothing
+    /// unless `--stats` was given.
+    pub fn report<W: Write>(&self, err_stream: &mut W) -> io::Result<()> {
+        let format = match self.option.stats {
+            Some(format) => format,
+            None => return Ok(()),
+        };
+        let mut total = Stats::default();
+        for (_, stats) in &self.file_stats {
+            total.add(stats);
+        }
+        let mut out = Vec::new();
+        match format {
+            StatsFormat::Table => {
+                out.extend_from_slice(
+                    format!(
+                        "{:>10} {:>12} {:>9} {:>12} {:>12} file\n",
+                        "lines", "bytes", "squeezed", "nonprinting", "unterminated"
+                    )
+                    .as_bytes(),
+                );
+                let total_row = (Path::new("total"), &total);
+                let rows = self.file_stats.iter().map(|(p, s)| (p.as_path(), s));
+                for (path, stats) in rows.chain(Some(total_row)) {
+                    out.extend_from_slice(
+                        format!(
+                            "{:>10} {:>12} {:>9} {:>12} {:>12} ",
+                            stats.lines,
+                            stats.bytes,
+                            stats.squeezed,
+                            stats.nonprinting,
+                            stats.unterminated
+                        )
+                        .as_bytes(),
+                    );
+                    out.extend_from_slice(&cliopt::os_bytes(path.as_os_str()));
+                    out.push(b'\n');
+                }
+            }
+            StatsFormat::Json => {
+                fn push_stats(out: &mut Vec<u8>, stats: &Stats) {
+                    out.extend_from_slice(
+                        format!(
+                            "\"lines\":{},\"bytes\":{},\"squeezed\":{},\
+                             \"nonprinting\":{},\"unterminated\":{}}}",
+                            stats.lines,
+                            stats.bytes,
+                            stats.squeezed,
+                            stats.nonprinting,
+                            stats.unterminated
+                        )
+                        .as_bytes(),
+                    );
+                }
+                out.extend_from_slice(b"{\"files\":[");
+                for (i, (path, stats)) in self.file_stats.iter().enumerate() {
+                    if i > 0 {
+                        out.push(b',');
+                    }
+                    out.extend_from_slice(b"{\"file\":");
+                    json::push_string(&mut out, &cliopt::os_bytes(path.as_os_str()));
+                    out.push(b',');
+                    push_stats(&mut out, stats);
+                }
+                out.extend_from_slice(b"],\"total\":{");
+                push_stats(&mut out, &total);
+                out.extend_from_slice(b"}\n");
+            }
+        }
+        err_stream.write_all(&out)
+    }
+
+    /// Transform one chunk of input into `out`. Line state is kept in `self`
+    /// so that a line may span several chunks (and several input files).
+    fn transform(&mut self, chunk: &[u8], out: &mut Vec<u8>) {
+        if self.option.stats.is_some() {
+            self.stats.count(chunk);
+        }
+        if self.option.json {
+            return self.transform_json(chunk, out);
+        }
+        for &b in chunk {
+            if self.pending_cr {
+                self.pending_cr = false;
+                if b == b'\n' {
+                    self.marker(out, "np", b"^M");
+                } else {
+                    self.literal(out, b'\r');
+                }
+            }
+            if self.at_line_start {
+                if b == b'\n' {
+                    if self.option.squeeze_blank {
+                        self.blank_count += 1;
+                        if self.blank_count > 1 {
+                            self.stats.squeezed += 1;
+                            continue;
+                        }
+                    }
+                    let numbered = self.option.number && !self.option.number_noblank;
+                    self.open_line(out, numbered);
+                    if self.option.profile == Profile::Bsd
+                        && self.option.number_noblank
+                        && self.option.show_ends
+                    {
+                        // BSD pads unnumbered empty lines to the number column
+                        out.extend_from_slice(b"      \t");
+                    }
+                } else {
+                    self.blank_count = 0;
+                    let numbered = self.option.number || self.option.number_noblank;
+                    self.open_line(out, numbered);
+                }
+            }
+            match b {
+                b'\n' => {
+                    if self.option.show_ends {
+                        self.marker(out, "eol", b"$");
+                    }
+                    if self.option.html {
+                        out.extend_from_slice(b"</span>");
+                    }
+                    out.push(b'\n');
+                    self.at_line_start = true;
+                    continue;
+                }
+                b'\t' if self.option.show_tabs => self.marker(out, "tab", b"^I"),
+                b'\r' if self.option.show_ends && !self.option.show_nonprinting => {
+                    // "\r\n" is displayed as "^M$"
+                    self.pending_cr = true;
+                }
+                b'\t' | 32..=126 => self.literal(out, b),
+                _ if self.option.show_nonprinting => {
+                    let mut notation = Vec::new();
+                    push_nonprinting(&mut notation, b);
+                    self.marker(out, "np", &notation);
+                }
+                _ => out.push(b),
+            }
+            self.at_line_start = false;
+        }
+    }
+
+    /// Begin an output line, with its number if `numbered`.
+    fn open_line(&mut self, out: &mut Vec<u8>, numbered: bool) {
+        let mut stamp = Vec::new();
+        if let Some(mode) = self.option.timestamp {
+            if self.timestamper.is_none() {
+                let format = match self.option.timestamp_format {
+                    Some(ref format) => format.as_str(),
+                    None => mode.default_format(),
+                };
+                self.timestamper = Some(Timestamper::new(mode, format, self.started));
+            }
+            let now = (self.clock)();
+            self.timestamper.as_mut().unwrap().stamp(now, &mut stamp);
+        }
+        if !self.option.html {
+            if !stamp.is_empty() {
+                self.marker(out, "ts", &stamp);
+                out.push(b' ');
+            }
+            if numbered {
+                let number = format!("{:>6}", self.number_count);
+                self.marker(out, "ln", number.as_bytes());
+                out.push(b'\t');
+                self.number_count += 1;
+            }
+            return;
+        }
+        // the -n counter names the line anchors even when it is not shown
+        let counted = numbered || !(self.option.number || self.option.number_noblank);
+        if counted {
+            out.extend_from_slice(
+                format!("<span class=\"line\" id=\"L{}\">", self.number_count).as_bytes(),
+            );
+        } else {
+            out.extend_from_slice(b"<span class=\"line\">");
+        }
+        if !stamp.is_empty() {
+            self.marker(out, "ts", &stamp);
+            out.push(b' ');
+        }
+        if !counted {
+            return;
+        }
+        if numbered {
+            out.extend_from_slice(
+                format!(
+                    "<a class=\"ln\" href=\"#L{0}\">{0:>6}</a>\t",
+                    self.number_count
+                )
+                .as_bytes(),
+            );
+        }
+        self.number_count += 1;
+    }
+
+    /// Append a byte of input text, escaped for HTML when needed.
+    fn literal(&self, out: &mut Vec<u8>, b: u8) {
+        if !self.option.html {
+            out.push(b);
+            return;
+        }
+        match b {
+            b'<' => out.extend_from_slice(b"&lt;"),
+            b'>' => out.extend_from_slice(b"&gt;"),
+            b'&' => out.extend_from_slice(b"&amp;"),
+            b'"' => out.extend_from_slice(b"&quot;"),
+            _ => out.push(b),
+        }
+    }
+
+    /// Append a marker such as "^I", "$" or "M-x", styled as `class` in HTML.
+    fn marker(&self, out: &mut Vec<u8>, class: &str, text: &[u8]) {
+        if self.colored() {
+            self.palette.paint(out, class, text);
+            return;
+        }
+        if !self.option.html {
+            out.extend_from_slice(text);
+            return;
+        }
+        out.extend_from_slice(format!("<span class=\"{}\">", class).as_bytes());
+        for &b in text {
+            self.literal(out, b);
+        }
+        out.extend_from_slice(b"</span>");
+    }
+
+    /// Collect lines for `--json`. `chunk` starts at `file_byte` and never
+    /// extends past the end of a line (see `select`).
+    fn transform_json(&mut self, chunk: &[u8], out: &mut Vec<u8>) {
+        if chunk.is_empty() {
+            return;
+        }
+        if self.record.is_none() {
+            self.record = Some(Record {
+                line: self.file_line,
+                offset: self.file_byte - 1,
+                whole: self.at_line_start,
+                text: Vec::new(),
+            });
+        }
+        let newline = chunk.last() == Some(&b'\n');
+        let text = if newline {
+            &chunk[..chunk.len() - 1]
+        } else {
+            chunk
+        };
+        self.record.as_mut().unwrap().text.extend_from_slice(text);
+        self.at_line_start = newline;
+        if newline {
+            self.finish_record(out, true);
+        }
+    }
+
+    /// Write the collected `--json` line, if any, as one JSON object. The
+    /// output is strict UTF-8 JSON; a file name or text that is not UTF-8 is
+    /// written lossily and repeated in hex as `file_bytes` or `text_bytes`.
+    fn finish_record(&mut self, out: &mut Vec<u8>, newline: bool) {
+        let record = match self.record.take() {
+            Some(record) => record,
+            None => return,
+        };
+        let blank = record.whole && record.text.is_empty();
+        if blank && self.option.squeeze_blank {
+            self.blank_count += 1;
+            if self.blank_count > 1 {
+                self.stats.squeezed += 1;
+                return;
+            }
+        } else {
+            self.blank_count = 0;
+        }
+        let number = if record.whole
+            && (self.option.number && !self.option.number_noblank
+                || self.option.number_noblank && !blank)
+        {
+            self.number_count += 1;
+            Some(self.number_count - 1)
+        } else {
+            None
+        };
+        let file_name = cliopt::os_bytes(self.file_name.as_os_str());
+        out.extend_from_slice(b"{\"file\":");
+        json::push_string(out, &file_name);
+        if std::str::from_utf8(&file_name).is_err() {
+            out.extend_from_slice(b",\"file_bytes\":");
+            json::push_hex(out, &file_name);
+        }
+        out.extend_from_slice(
+            format!(",\"line\":{},\"offset\":{}", record.line, record.offset).as_bytes(),
+        );
+        if let Some(number) = number {
+            out.extend_from_slice(format!(",\"number\":{}", number).as_bytes());
+        }
+        out.extend_from_slice(b",\"text\":");
+        json::push_string(out, &record.text);
+        if std::str::from_utf8(&record.text).is_err() {
+            out.extend_from_slice(b",\"text_bytes\":");
+            json::push_hex(out, &record.text);
+        }
+        out.extend_from_slice(format!(",\"newline\":{}}}\n", newline).as_bytes());
+    }
+
+    fn cat<R, W, E>(
+        &mut self,
+        in_stream: &mut R,
+        out_stream: &mut W,
+        err_stream: &mut E,
+    ) -> Result<(), ()>
+    where
+        R: BufRead + ?Sized,
+        W: Write,
+        E: Write,
+    {
+        if !self.option.decompress {
+            return self.copy(in_stream, out_stream, err_stream);
+        }
+        match decompress::reader(&mut *in_stream) {
+            Ok(mut decoded) => self.copy(&mut *decoded, out_stream, err_stream),
+            Err(err) => {
+                writeln!(
+                    err_stream,
+                    "cat: {}: {}",
+                    self.file_name.display(),
+                    describe(&err)
+                )
+                .unwrap();
+                Err(())
+            }
+        }
+    }
+
+    fn copy<R, W, E>(
+        &mut self,
+        in_stream: &mut R,
+        out_stream: &mut W,
+        err_stream: &mut E,
+    ) -> Result<(), ()>
+    where
+        R: BufRead + ?Sized,
+        W: Write,
+        E: Write,
+    {
+        let shown = match self.check_binary(in_stream, err_stream) {
+            Some(shown) => shown,
+            None => return Ok(()),
+        };
+        self.option.show_nonprinting |= shown;
+        let result = self
+            .begin_file(out_stream, err_stream)
+            .and_then(|_| self.read_chunks(in_stream, out_stream, err_stream))
+            .and_then(|_| self.end_file(out_stream, err_stream));
+        if shown {
+            self.option.show_nonprinting = false;
+        }
+        result
+    }
+
+    /// Apply `--binary` to the input about to be read. `None` means it was
+    /// refused, which has been reported; otherwise whether it is binary
+    /// and to be shown as with `-v`.
+    fn check_binary<R, E>(&mut self, in_stream: &mut R, err_stream: &mut E) -> Option<bool>
+    where
+        R: BufRead + ?Sized,
+        E: Write,
+    {
+        let action = match self.option.binary {
+            Some(action) if !self.option.force && !self.option.show_nonprinting => action,
+            _ => return Some(false),
+        };
+        // a read error here is met again, and reported, later
+        let binary = in_stream.fill_buf().is_ok_and(looks_binary);
+        if binary && action == BinaryAction::Refuse {
+            writeln!(
+                err_stream,
+                "cat: {}: binary file (use -v or --force)",
+                self.file_name.display()
+            )
+            .unwrap();
+            self.failed = true;
+            return None;
+        }
+        Some(binary && self.terminal_output)
+    }
+
+    fn begin_file<W: Write, E: Write>(
+        &mut self,
+        out_stream: &mut W,
+        err_stream: &mut E,
+    ) -> Result<(), ()> {
+        if self.option.html {
+            let mut rendered: Vec<u8> = Vec::new();
+            if !self.html_started {
+                self.html_started = true;
+                rendered.extend_from_slice(HTML_STYLE);
+            }
+            if self.option.print_headers {
+                rendered.extend_from_slice(b"<div class=\"cat-file\">==&gt; ");
+                if self.file_name.as_os_str() == "-" {
+                    rendered.extend_from_slice(b"standard input");
+                } else {
+                    for &b in cliopt::os_bytes(self.file_name.as_os_str()).iter() {
+                        self.literal(&mut rendered, b);
+                    }
+                }
+                rendered.extend_from_slice(b" &lt;==</div>\n");
+            }
+            rendered.extend_from_slice(b"<pre class=\"cat\">");
+            if let Err(err) = out_stream.write_all(&rendered) {
+                write_error(err_stream, &err);
+                return Err(());
+            }
+        }
+        Ok(())
+    }
+
+    /// Transform everything up to the end of `in_stream`. Returns whether
+    /// the selected ranges ended before that.
+    fn read_chunks<R, W, E>(
+        &mut self,
+        in_stream: &mut R,
+        out_stream: &mut W,
+        err_stream: &mut E,
+    ) -> Result<bool, ()>
+    where
+        R: BufRead + ?Sized,
+        W: Write,
+        E: Write,
+    {
+        let plain = self.is_plain();
+        // --json needs to see where each line starts, which `select` tracks
+        let ranged = self.option.lines.is_some() || self.option.bytes.is_some() || self.option.json;
+        let mut rendered: Vec<u8> = Vec::new();
+        let mut done = false;
+        loop {
+            let consumed = match in_stream.fill_buf() {
+                Ok(chunk) => {
+                    if chunk.is_empty() {
+                        break;
+                    }
+                    let mut used = chunk.len();
+                    let out = if ranged {
+                        rendered.clear();
+                        let (n, past) = self.select(chunk, &mut rendered);
+                        used = n;
+                        done = past;
+                        rendered.as_slice()
+                    } else if plain {
+                        chunk
+                    } else {
+                        rendered.clear();
+                        self.transform(chunk, &mut rendered);
+                        rendered.as_slice()
+                    };
+                    let flush = match self.option.buffering {
+                        Buffering::Full => false,
+                        Buffering::Line => out.contains(&b'\n'),
+                        Buffering::Unbuffered => true,
+                    };
+                    if let Err(err) = out_stream.write_all(out) {
+                        write_error(err_stream, &err);
+                        return Err(());
+                    }
+                    if flush {
+                        if let Err(err) = out_stream.flush() {
+                            write_error(err_stream, &err);
+                            return Err(());
+                        }
+                    }
+                    used
+                }
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    writeln!(
+                        err_stream,
+                        "cat: {}: {}",
+                        self.file_name.display(),
+                        describe(&err)
+                    )
+                    .unwrap();
+                    return Err(());
+                }
+            };
+            in_stream.consume(consumed);
+            self.file_offset += consumed as u64;
+            if done {
+                break;
+            }
+        }
+        Ok(done)
+    }
+
+    /// Remember how far the current file has been read.
+    fn record_offset(&mut self) {
+        let (offsets, name) = (&mut self.offsets, &self.file_name);
+        match offsets.iter_mut().find(|(p, _)| p == name) {
+            Some(entry) => entry.1 = self.file_offset,
+            None => offsets.push((name.clone(), self.file_offset)),
+        }
+    }
+
+    /// Finish the output for the current file once its input has ended.
+    fn end_file<W: Write, E: Write>(
+        &mut self,
+        out_stream: &mut W,
+        err_stream: &mut E,
+    ) -> Result<(), ()> {
+        if self.option.stats.is_some() {
+            let mut stats = std::mem::take(&mut self.stats);
+            if stats.bytes > 0 && !self.at_line_start {
+                stats.lines += 1;
+                stats.unterminated += 1;
+            }
+            self.file_stats.push((self.file_name.clone(), stats));
+        }
+        let mut tail = Vec::new();
+        if self.record.is_some() {
+            // a last line without newline ends with its file
+            self.finish_record(&mut tail, false);
+            self.at_line_start = true;
+        }
+        self.file_line = 1;
+        self.file_byte = 1;
+        self.record_offset();
+        self.file_offset = 0;
+        if self.pending_cr {
+            self.pending_cr = false;
+            tail.push(b'\r');
+        }
+        if self.option.html {
+            // each file is its own <pre> block, so close a last unterminated line
+            let close: &[u8] = if self.at_line_start {
+                b"</pre>\n"
+            } else {
+                b"</span></pre>\n"
+            };
+            self.at_line_start = true;
+            tail.extend_from_slice(close);
+        }
+        let result = out_stream.write_all(&tail).and_then(|_| {
+            if self.option.buffering != Buffering::Full {
+                out_stream.flush()
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(err) = result {
+            write_error(err_stream, &err);
+            return Err(());
+        }
+        Ok(())
+    }
+
+    /// Copy the file at `path`, then keep copying what is appended to it
+    /// until `idle` returns false. `idle` is called with the output flushed
+    /// whenever it has caught up with the file. A truncated file is read
+    /// again from the start and a replaced one is reopened by name, with
+    /// numbering carrying on throughout. Call after `start_file`, instead
+    /// of `run`; a saved state is resumed from as in `resume_input`.
+    #[allow(clippy::result_unit_err)]
+    pub fn follow<W, E, F>(
+        &mut self,
+        path: &Path,
+        out_stream: &mut W,
+        err_stream: &mut E,
+        idle: F,
+    ) -> Result<(), ()>
+    where
+        W: Write,
+        E: Write,
+        F: FnMut(&Cat) -> bool,
+    {
+        match File::open(path) {
+            Ok(file) => self.follow_file(path, file, out_stream, err_stream, idle),
+            Err(err) => {
+                writeln!(err_stream, "cat: {}: {}", path.display(), describe(&err)).unwrap();
+                Err(())
+            }
+        }
+    }
+
+    /// `follow` with `file` already opened from `path`. `--binary` is
+    /// applied to what the file holds at the start.
+    fn follow_file<W, E, F>(
+        &mut self,
+        path: &Path,
+        mut file: File,
+        out_stream: &mut W,
+        err_stream: &mut E,
+        mut idle: F,
+    ) -> Result<(), ()>
+    where
+        W: Write,
+        E: Write,
+        F: FnMut(&Cat) -> bool,
+    {
+        let mut watcher = follow::Watcher::new(path, follow::POLL_INTERVAL);
+        let _ = self.resume_input(path, &mut file);
+        // one reader throughout, so that what the binary check read is kept
+        let mut reader = io::BufReader::new(file);
+        let shown = match self.check_binary(&mut reader, err_stream) {
+            Some(shown) => shown,
+            None => return Ok(()),
+        };
+        self.option.show_nonprinting |= shown;
+        let result = self
+            .begin_file(out_stream, err_stream)
+            .and_then(|_| {
+                self.follow_reader(
+                    path,
+                    &mut reader,
+                    out_stream,
+                    err_stream,
+                    &mut idle,
+                    &mut watcher,
+                )
+            })
+            .and_then(|_| self.end_file(out_stream, err_stream));
+        if shown {
+            self.option.show_nonprinting = false;
+        }
+        result
+    }
+
+    fn follow_reader<W, E, F>(
+        &mut self,
+        path: &Path,
+        reader: &mut io::BufReader<File>,
+        out_stream: &mut W,
+        err_stream: &mut E,
+        idle: &mut F,
+        watcher: &mut follow::Watcher,
+    ) -> Result<(), ()>
+    where
+        W: Write,
+        E: Write,
+        F: FnMut(&Cat) -> bool,
+    {
+        loop {
+            if self.read_chunks(reader, out_stream, err_stream)? {
+                return Ok(());
+            }
+            // a missing path is a rotation in progress: keep the old file
+            if let (Ok(opened), Ok(current)) = (reader.get_ref().metadata(), fs::metadata(path)) {
+                if !follow::same_file(&opened, &current) {
+                    match File::open(path) {
+                        Ok(new) => {
+                            // the old file was drained above
+                            writeln!(
+                                err_stream,
+                                "cat: '{}' has been replaced; following new file",
+                                path.display()
+                            )
+                            .unwrap();
+                            *reader = io::BufReader::new(new);
+                            self.file_offset = 0;
+                            self.file_line = 1;
+                            self.file_byte = 1;
+                            continue;
+                        }
+                        Err(ref err) if err.kind() == io::ErrorKind::NotFound => (),
+                        Err(err) => {
+                            writeln!(err_stream, "cat: {}: {}", path.display(), describe(&err))
+                                .unwrap();
+                            return Err(());
+                        }
+                    }
+                } else if current.len() < self.file_offset {
+                    writeln!(err_stream, "cat: {}: file truncated", path.display()).unwrap();
+                    if reader.seek(io::SeekFrom::Start(0)).is_err() {
+                        return Err(());
+                    }
+                    self.file_offset = 0;
+                    self.file_line = 1;
+                    self.file_byte = 1;
+                    continue;
+                }
+            }
+            if let Err(err) = out_stream.flush() {
+                write_error(err_stream, &err);
+                return Err(());
+            }
+            self.record_offset();
+            if !idle(self) {
+                return Ok(());
+            }
+            watcher.wait();
+        }
+    }
+
+    #[allow(clippy::result_unit_err)]
+    pub fn run<R, W, E>(
+        &mut self,
+        in_stream: &mut R,
+        out_stream: &mut W,
+        err_stream: &mut E,
+    ) -> Result<(), ()>
+    where
+        R: BufRead + ?Sized,
+        W: Write,
+        E: Write,
+    {
+        if self.option.display_version {
+            self.version(out_stream);
+            Err(())
+        } else if self.option.display_help {
+            self.help(out_stream);
+            Err(())
+        } else {
+            self.cat(in_stream, out_stream, err_stream)
+        }
+    }
+}
+
+impl Cat {
+    /// Write the state to the `--state` file, if any, replacing the old
+    /// state in one step so that a crash never leaves half of it.
+    fn save_state(&self) -> io::Result<()> {
+        let state_file = match self.state_file() {
+            Some(state_file) => state_file,
+            None => return Ok(()),
+        };
+        let mut temp = state_file.as_os_str().to_os_string();
+        temp.push(".tmp");
+        fs::write(&temp, self.state().to_string())?;
+        fs::rename(&temp, state_file)
+    }
+
+    /// Load the `--state` file, if any; a missing one means a first run.
+    fn load_state(&mut self) -> Result<(), String> {
+        let state_file = match self.state_file() {
+            Some(state_file) => state_file.to_path_buf(),
+            None => return Ok(()),
+        };
+        if let Ok(text) = fs::read_to_string(&state_file) {
+            match text.parse::<CatState>() {
+                Ok(state) => self.set_state(&state),
+                Err(err) => return Err(format!("cat: {}: {}", state_file.display(), err)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `fname` is the regular file standard output appends to, and
+    /// is not empty: reading it would read back what cat writes, forever.
+    /// GNU cat refuses such a file too. `Err` means output failed.
+    fn is_output(&self, fname: &Path, ctx: &mut Context) -> Result<bool, ()> {
+        let input = match ctx.fs.metadata(fname) {
+            Ok(input) if input.kind == FileKind::File => input,
+            _ => return Ok(false),
+        };
+        match ctx.fs.stdout() {
+            Some(output) if output.same_file(&input) => (),
+            _ => return Ok(false),
+        }
+        // the size counts what earlier files added
+        ctx.stdout
+            .flush()
+            .map_err(|err| write_error(ctx.stderr, &err))?;
+        Ok(ctx.fs.stdout().is_some_and(|output| output.len > 0))
+    }
+
+    /// Report that saving the `--state` file failed with `err`.
+    fn state_error(&mut self, err: &io::Error, ctx: &mut Context) {
+        self.state_failed = true;
+        if let Some(state_file) = self.state_file() {
+            writeln!(
+                ctx.stderr,
+                "cat: {}: {}",
+                state_file.display(),
+                describe(err)
+            )
+            .unwrap();
+        }
+    }
+
+    /// Follow the FILE operand `fname`, opened as `file`, saving the state
+    /// each time it has been caught up with. Following stops if that fails.
+    fn follow_operand(&mut self, fname: &Path, file: File, ctx: &mut Context) -> Result<bool, ()> {
+        self.start_file(fname, &mut ctx.stdout)
+            .map_err(|err| write_error(ctx.stderr, &err))?;
+        let mut saved = Ok(());
+        self.follow_file(fname, file, &mut ctx.stdout, &mut ctx.stderr, |cat| {
+            saved = cat.save_state();
+            saved.is_ok()
+        })?;
+        match saved {
+            Ok(()) => Ok(true),
+            Err(err) => {
+                self.state_error(&err, ctx);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Cat one FILE operand. `Err` means output failed and cat must stop;
+    /// `Ok(false)` that the file could not be read. With `followed`, a
+    /// regular file is then followed; anything else is read once.
+    fn cat_operand(&mut self, fname: &Path, followed: bool, ctx: &mut Context) -> Result<bool, ()> {
+        if fname.as_os_str() == "-" {
+            self.start_file(fname, &mut ctx.stdout)
+                .map_err(|err| write_error(ctx.stderr, &err))?;
+            return self
+                .run(&mut ctx.stdin, &mut ctx.stdout, &mut ctx.stderr)
+                .map(|_| true);
+        }
+        if ctx.fs.may_block(fname) {
+            // let the reader see earlier files while this one waits for its peer
+            ctx.stdout
+                .flush()
+                .map_err(|err| write_error(ctx.stderr, &err))?;
+        }
+        let input = match ctx.fs.open(fname) {
+            Ok(input) => input,
+            Err(err) => {
+                ctx.stdout
+                    .flush()
+                    .map_err(|err| write_error(ctx.stderr, &err))?;
+                writeln!(ctx.stderr, "cat: {}: {}", fname.display(), describe(&err)).unwrap();
+                return Ok(false);
+            }
+        };
+        if self.is_output(fname, ctx)? {
+            writeln!(
+                ctx.stderr,
+                "cat: {}: input file is output file",
+                fname.display()
+            )
+            .unwrap();
+            return Ok(false);
+        }
+        let mut input = match (followed, input) {
+            (true, Input::File(file)) => return self.follow_operand(fname, file, ctx),
+            (_, input) => input,
+        };
+        // pipes and terminals cannot seek; they are read through instead
+        if let Some(file) = input.file_mut() {
+            if self.resume_input(fname, file).unwrap_or(0) == 0 {
+                let _ = self.seek_input(file);
+            }
+        }
+        self.start_file(fname, &mut ctx.stdout)
+            .map_err(|err| write_error(ctx.stderr, &err))?;
+        self.run(
+            &mut io::BufReader::new(input),
+            &mut ctx.stdout,
+            &mut ctx.stderr,
+        )
+        .map(|_| true)
+    }
+}
+
+impl Utility for Cat {
+    fn name(&self) -> &str {
+        "cat"
+    }
+
+    fn parse_args(&mut self, args: &[OsString]) -> Result<(), String> {
+        self.operands = self.parse(args)?;
+        Ok(())
+    }
+
+    fn execute(&mut self, ctx: &mut Context) -> i32 {
+        if self.option.display_version {
+            self.version(&mut ctx.stdout);
+            return 0;
+        }
+        if self.option.display_help {
+            self.help(&mut ctx.stdout);
+            return 0;
+        }
+        let status = self.cat_all(ctx);
+        // the report covers what was read, however catting ended
+        self.report(&mut ctx.stderr).unwrap();
+        status
+    }
+}
+
+impl Cat {
+    /// Cat every FILE operand with the settings from `ctx`, returning the
+    /// exit status.
+    fn cat_all(&mut self, ctx: &mut Context) -> i32 {
+        if self.locks_output() {
+            if let Err(err) = lock_stdout() {
+                writeln!(ctx.stderr, "cat: stdout: {}", describe(&err)).unwrap();
+                return 1;
+            }
+        }
+        self.set_terminal_output(ctx.stdout_is_terminal);
+        let no_color = ctx.var("NO_COLOR").map(|v| v.to_os_string());
+        let palette = ctx.var("RTW_CAT_COLORS").map(|v| v.to_os_string());
+        self.set_color_env(no_color.as_deref(), palette.as_deref());
+        if ctx.stdout_is_terminal && self.buffering() == Buffering::Full {
+            // behave like stdio: line buffered on terminals, fully buffered otherwise
+            self.set_buffering(Buffering::Line);
+        }
+        if let Err(message) = self.load_state() {
+            writeln!(ctx.stderr, "{}", message).unwrap();
+            return 1;
+        }
+
+        let mut files = std::mem::take(&mut self.operands);
+        if files.is_empty() {
+            files.push(PathBuf::from("-"));
+        }
+        let mut status = 0;
+        let operands = self.expand_operands_in(ctx.fs, &files);
+        // --follow applies to the last file; standard input just ends
+        let followed = match operands.last() {
+            Some(Ok(fname)) if self.follows() && fname.as_os_str() != "-" => {
+                Some(operands.len() - 1)
+            }
+            _ => None,
+        };
+        for (index, operand) in operands.into_iter().enumerate() {
+            match operand {
+                Ok(fname) => match self.cat_operand(&fname, followed == Some(index), ctx) {
+                    Ok(true) => (),
+                    Ok(false) => status = 1,
+                    Err(()) => return 1,
+                },
+                Err(message) => {
+                    if let Err(err) = ctx.stdout.flush() {
+                        write_error(ctx.stderr, &err);
+                        return 1;
+                    }
+                    writeln!(ctx.stderr, "{}", message).unwrap();
+                    status = 1;
+                }
+            }
+        }
+        if let Err(err) = ctx.stdout.flush() {
+            write_error(ctx.stderr, &err);
+            return 1;
+        }
+        if !self.state_failed {
+            if let Err(err) = self.save_state() {
+                self.state_error(&err, ctx);
+            }
+        }
+        if self.state_failed {
+            status = 1;
+        }
+        if self.failed() {
+            status = 1;
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::vfs::MemoryFs;
+    use super::*;
+    use std::fs::File;
+    use std::io::{BufReader, SeekFrom};
+    use std::process;
+
+    #[test]
+    fn test_build() {
+        let c = CatBuilder::new().build();
+        assert_eq!(CmdOption::default(), c.option);
+
+        let c = CatBuilder::new().with_show_all().build();
+        assert_eq!(
+            CmdOption {
+                show_ends: true,
+                show_tabs: true,
+                show_nonprinting: true,
+                ..Default::default()
+            },
+            c.option
+        );
+
+        let c = CatBuilder::new().with_number_nonblank().build();
+        assert_eq!(
+            CmdOption {
+                number_noblank: true,
+                ..Default::default()
+            },
+            c.option
+        );
+
+        let c = CatBuilder::new().with_e().build();
+        assert_eq!(
+            CmdOption {
+                show_ends: true,
+                show_nonprinting: true,
+                ..Default::default()
+            },
+            c.option
+        );
+
+        let c = CatBuilder::new().with_show_ends().build();
+        assert_eq!(
+            CmdOption {
+                show_ends: true,
+                ..Default::default()
+            },
+            c.option
+        );
+
+        let c = CatBuilder::new().with_number().build();
+        assert_eq!(
+            CmdOption {
+                number: true,
+                ..Default::default()
+            },
+            c.option
+        );
+
+        let c = CatBuilder::new().with_squeeze_blank().build();
         assert_eq!(
             CmdOption {
                 squeeze_blank: true,
@@ -389,6 +2211,66 @@ mod tests {
             c.option
         );
 
+        let c = CatBuilder::new().with_unbuffered().build();
+        assert_eq!(
+            CmdOption {
+                buffering: Buffering::Unbuffered,
+                ..Default::default()
+            },
+            c.option
+        );
+
+        let c = CatBuilder::new().with_line_buffered().build();
+        assert_eq!(
+            CmdOption {
+                buffering: Buffering::Line,
+                ..Default::default()
+            },
+            c.option
+        );
+
+        let c = CatBuilder::new().with_recursive().build();
+        assert_eq!(
+            CmdOption {
+                recursion: Recursion::NoFollow,
+                ..Default::default()
+            },
+            c.option
+        );
+
+        let c = CatBuilder::new().with_dereference_recursive().build();
+        assert_eq!(
+            CmdOption {
+                recursion: Recursion::Follow,
+                ..Default::default()
+            },
+            c.option
+        );
+
+        let c = CatBuilder::new()
+            .with_glob()
+            .with_headers()
+            .with_number_per_file()
+            .build();
+        assert_eq!(
+            CmdOption {
+                expand_globs: true,
+                print_headers: true,
+                number_per_file: true,
+                ..Default::default()
+            },
+            c.option
+        );
+
+        let c = CatBuilder::new().with_decompress().build();
+        assert_eq!(
+            CmdOption {
+                decompress: true,
+                ..Default::default()
+            },
+            c.option
+        );
+
         let c = CatBuilder::new().with_display_help().build();
         assert_eq!(
             CmdOption {
@@ -409,6 +2291,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::needless_borrows_for_generic_args)]
     fn test_parse() {
         {
             // "-A" | "--show-all"
@@ -547,12 +2430,129 @@ mod tests {
         }
 
         {
-            // "-u"
-            let expects = CmdOption::default();
+            // "-u" | "--unbuffered"
+            let expects = CmdOption {
+                buffering: Buffering::Unbuffered,
+                ..Default::default()
+            };
+
             let mut c = CatBuilder::new().build();
             let files = c.parse(&["-u"]);
             assert_eq!(expects, c.option);
             assert_eq!(files, Ok(Vec::new()));
+
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(&["--unbuffered"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(files, Ok(Vec::new()));
+        }
+
+        {
+            // "--line-buffered"
+            let expects = CmdOption {
+                buffering: Buffering::Line,
+                ..Default::default()
+            };
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(&["--line-buffered"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(files, Ok(Vec::new()));
+        }
+
+        {
+            // "-r" | "--recursive"
+            let expects = CmdOption {
+                recursion: Recursion::NoFollow,
+                ..Default::default()
+            };
+
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["-r"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(files, Ok(Vec::new()));
+
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["--recursive"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(files, Ok(Vec::new()));
+        }
+
+        {
+            // "-R" | "--dereference-recursive"
+            let expects = CmdOption {
+                recursion: Recursion::Follow,
+                ..Default::default()
+            };
+
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["-R"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(files, Ok(Vec::new()));
+
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["--dereference-recursive"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(files, Ok(Vec::new()));
+        }
+
+        {
+            // "--glob"
+            let expects = CmdOption {
+                expand_globs: true,
+                ..Default::default()
+            };
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["--glob"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(files, Ok(Vec::new()));
+        }
+
+        {
+            // "-H" | "--headers"
+            let expects = CmdOption {
+                print_headers: true,
+                ..Default::default()
+            };
+
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["-H"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(files, Ok(Vec::new()));
+
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["--headers"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(files, Ok(Vec::new()));
+        }
+
+        {
+            // "--number-per-file"
+            let expects = CmdOption {
+                number_per_file: true,
+                ..Default::default()
+            };
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["--number-per-file"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(files, Ok(Vec::new()));
+        }
+
+        {
+            // "-z" | "--decompress"
+            let expects = CmdOption {
+                decompress: true,
+                ..Default::default()
+            };
+
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["-z"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(files, Ok(Vec::new()));
+
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["--decompress"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(files, Ok(Vec::new()));
         }
 
         {
@@ -573,6 +2573,262 @@ mod tests {
             assert_eq!(files, Ok(Vec::new()));
         }
 
+        {
+            // "--json"
+            let expects = CmdOption {
+                json: true,
+                ..Default::default()
+            };
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["--json"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(files, Ok(Vec::new()));
+        }
+
+        {
+            // "--html"
+            let expects = CmdOption {
+                html: true,
+                ..Default::default()
+            };
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["--html"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(files, Ok(Vec::new()));
+        }
+
+        {
+            // "--stats" | "--stats=table" | "--stats=json"
+            for (arg, format) in [
+                ("--stats", StatsFormat::Table),
+                ("--stats=table", StatsFormat::Table),
+                ("--stats=json", StatsFormat::Json),
+            ]
+            .iter()
+            {
+                let mut c = CatBuilder::new().build();
+                let files = c.parse([arg]);
+                assert_eq!(
+                    CmdOption {
+                        stats: Some(*format),
+                        ..Default::default()
+                    },
+                    c.option
+                );
+                assert_eq!(files, Ok(Vec::new()));
+            }
+        }
+
+        {
+            // BSD profile: clustered "-belnstuv", ending at the first operand
+            let expects = CmdOption {
+                profile: Profile::Bsd,
+                number: true,
+                number_noblank: true,
+                show_ends: true,
+                show_nonprinting: true,
+                lock_output: true,
+                ..Default::default()
+            };
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["--profile=bsd", "-be", "-l", "a", "-n", "--", "b"]);
+            assert_eq!(expects, c.option);
+            assert!(c.locks_output());
+            assert_eq!(
+                files,
+                Ok(vec![
+                    PathBuf::from("a"),
+                    PathBuf::from("-n"),
+                    PathBuf::from("--"),
+                    PathBuf::from("b")
+                ])
+            );
+
+            let mut c = CatBuilder::new().with_profile(Profile::Bsd).build();
+            let files = c.parse(["-u", "--", "-n"]);
+            assert_eq!(c.option.buffering, Buffering::Unbuffered);
+            assert_eq!(files, Ok(vec![PathBuf::from("-n")]));
+
+            let mut c = CatBuilder::new().with_profile(Profile::Bsd).build();
+            assert_eq!(
+                c.parse(["-nA"]),
+                Err("cat: illegal option -- A\nusage: cat [-belnstuv] [file ...]".to_string())
+            );
+            let mut c = CatBuilder::new().with_profile(Profile::Bsd).build();
+            assert!(c.parse(["--help"]).is_err());
+            let mut c = CatBuilder::new().build();
+            assert!(c.parse(["--profile=sysv"]).is_err());
+        }
+
+        {
+            // "--color[=WHEN]"
+            for (arg, when) in [
+                ("--color", ColorWhen::Always),
+                ("--color=always", ColorWhen::Always),
+                ("--color=auto", ColorWhen::Auto),
+                ("--color=never", ColorWhen::Never),
+            ]
+            .iter()
+            {
+                let mut c = CatBuilder::new().build();
+                let files = c.parse(["-A", arg]);
+                assert_eq!(
+                    CmdOption {
+                        show_nonprinting: true,
+                        show_ends: true,
+                        show_tabs: true,
+                        color: *when,
+                        ..Default::default()
+                    },
+                    c.option
+                );
+                assert_eq!(files, Ok(Vec::new()));
+            }
+            let mut c = CatBuilder::new().build();
+            assert!(c.parse(["--color=sometimes"]).is_err());
+        }
+
+        {
+            // "--binary=refuse|show" | "--force"
+            let expects = CmdOption {
+                binary: Some(BinaryAction::Refuse),
+                ..Default::default()
+            };
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["--binary=refuse"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(files, Ok(Vec::new()));
+
+            let expects = CmdOption {
+                binary: Some(BinaryAction::Show),
+                force: true,
+                ..Default::default()
+            };
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["--binary=show", "--force"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(files, Ok(Vec::new()));
+        }
+
+        {
+            // "--follow"
+            let expects = CmdOption {
+                follow: true,
+                ..Default::default()
+            };
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["--follow", "log"]);
+            assert_eq!(expects, c.option);
+            assert!(c.follows());
+            assert_eq!(files, Ok(vec![PathBuf::from("log")]));
+
+            // a compressed file cannot be followed
+            for args in [["-z", "--follow"], ["--follow", "--decompress"]].iter() {
+                let mut c = CatBuilder::new().build();
+                assert_eq!(
+                    c.parse(args),
+                    Err("cat: --follow cannot be used with --decompress\n\
+                         Try 'cat --help' for more information."
+                        .to_string())
+                );
+            }
+        }
+
+        {
+            // "--state=FILE"
+            let expects = CmdOption {
+                number: true,
+                state_file: Some(PathBuf::from("/tmp/cat.state")),
+                ..Default::default()
+            };
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["-n", "--state=/tmp/cat.state", "log"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(c.state_file(), Some(Path::new("/tmp/cat.state")));
+            assert_eq!(files, Ok(vec![PathBuf::from("log")]));
+
+            // offsets into decompressed data cannot be resumed from
+            for args in [["-z", "--state=s"], ["--state=s", "--decompress"]].iter() {
+                let mut c = CatBuilder::new().build();
+                assert_eq!(
+                    c.parse(args),
+                    Err("cat: --state cannot be used with --decompress\n\
+                         Try 'cat --help' for more information."
+                        .to_string())
+                );
+            }
+        }
+
+        {
+            // "--timestamp[=MODE]" | "--timestamp-format=FORMAT"
+            for (args, mode) in [
+                (
+                    ["--timestamp", "--timestamp-format=%T"],
+                    TimestampMode::Absolute,
+                ),
+                (
+                    ["--timestamp=absolute", "--timestamp-format=%T"],
+                    TimestampMode::Absolute,
+                ),
+                (
+                    ["--timestamp=relative", "--timestamp-format=%T"],
+                    TimestampMode::Relative,
+                ),
+                (
+                    ["--timestamp=delta", "--timestamp-format=%T"],
+                    TimestampMode::Delta,
+                ),
+            ]
+            .iter()
+            {
+                let mut c = CatBuilder::new().build();
+                let files = c.parse(args);
+                assert_eq!(
+                    CmdOption {
+                        timestamp: Some(*mode),
+                        timestamp_format: Some("%T".to_string()),
+                        ..Default::default()
+                    },
+                    c.option
+                );
+                assert_eq!(files, Ok(Vec::new()));
+            }
+
+            let mut c = CatBuilder::new().build();
+            assert!(c.parse(["--timestamp=sometimes"]).is_err());
+        }
+
+        {
+            // "--lines=START:END" | "--bytes=START:END"
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["--lines=3:5", "--bytes=:10"]);
+            assert_eq!(
+                CmdOption {
+                    lines: Some(Range::new(3, 5)),
+                    bytes: Some(Range::new(1, 10)),
+                    ..Default::default()
+                },
+                c.option
+            );
+            assert_eq!(files, Ok(Vec::new()));
+
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["--lines=7:"]);
+            assert_eq!(
+                CmdOption {
+                    lines: Some(Range::new(7, u64::MAX)),
+                    ..Default::default()
+                },
+                c.option
+            );
+            assert_eq!(files, Ok(Vec::new()));
+
+            for bad in ["--lines=5:3", "--lines=0:3", "--bytes=1", "--bytes=a:b"].iter() {
+                let mut c = CatBuilder::new().build();
+                assert!(c.parse([bad]).is_err(), "{}", bad);
+            }
+        }
+
         {
             // "--help"
             let expects = CmdOption {
@@ -596,15 +2852,67 @@ mod tests {
             assert_eq!(expects, c.option);
             assert_eq!(files, Ok(Vec::new()));
         }
+
+        {
+            // clustered short options, mixed with operands
+            let expects = CmdOption {
+                number: true,
+                show_ends: true,
+                squeeze_blank: true,
+                ..Default::default()
+            };
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["-nE", "a", "-s"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(files, Ok(vec![PathBuf::from("a")]));
+
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["-nq"]);
+            assert_eq!(
+                files,
+                Err("cat: invalid option -- 'q'\n\
+                     Try 'cat --help' for more information."
+                    .to_string())
+            );
+
+            // a cluster that is not UTF-8 is an error, not a panic
+            #[cfg(unix)]
+            {
+                use std::os::unix::ffi::OsStrExt;
+                let mut c = CatBuilder::new().build();
+                let files = c.parse([OsStr::from_bytes(b"-n\xff")]);
+                assert!(files
+                    .unwrap_err()
+                    .starts_with("cat: invalid option -- '\u{fffd}'\n"));
+            }
+        }
+
+        {
+            // "--": the rest are operands
+            let expects = CmdOption {
+                number: true,
+                ..Default::default()
+            };
+            let mut c = CatBuilder::new().build();
+            let files = c.parse(["-n", "--", "-E", "--", "-"]);
+            assert_eq!(expects, c.option);
+            assert_eq!(
+                files,
+                Ok(vec![
+                    PathBuf::from("-E"),
+                    PathBuf::from("--"),
+                    PathBuf::from("-")
+                ])
+            );
+        }
     }
 
     #[test]
+    #[allow(clippy::needless_borrows_for_generic_args)]
     fn test_cat() {
         const TEST_DATA_PATH: &str = "ci-tests/test-data/cat_test.txt";
-        let mut file = File::open(&TEST_DATA_PATH).expect(&format!(
-            "cat: {}: No such file or directory",
-            TEST_DATA_PATH
-        ));
+        let mut file = File::open(TEST_DATA_PATH)
+            .unwrap_or_else(|_| panic!("cat: {}: No such file or directory", TEST_DATA_PATH));
         let options = ["-A", "-b", "-e", "-E", "-n", "-s", "-t", "-T", "-u", "-v"];
 
         for o in options.iter() {
@@ -628,4 +2936,998 @@ mod tests {
             file.seek(SeekFrom::Start(0)).expect("Failed to seek file");
         }
     }
+
+    /// Records the bytes that were written at each flush.
+    #[derive(Default)]
+    struct FlushRecorder {
+        pending: Vec<u8>,
+        flushed: Vec<Vec<u8>>,
+    }
+
+    impl Write for FlushRecorder {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.pending.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            if !self.pending.is_empty() {
+                self.flushed.push(self.pending.split_off(0));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_buffering() {
+        // the read buffer capacity decides the chunks handed to the transform
+        let input: &[u8] = b"ab\ncd\nef";
+
+        let mut c = CatBuilder::new().with_number().build();
+        let mut out = FlushRecorder::default();
+        c.run(
+            &mut BufReader::with_capacity(4, input),
+            &mut out,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert!(out.flushed.is_empty());
+        assert_eq!(out.pending, b"     1\tab\n     2\tcd\n     3\tef".to_vec());
+
+        let mut c = CatBuilder::new().with_unbuffered().build();
+        let mut out = FlushRecorder::default();
+        c.run(
+            &mut BufReader::with_capacity(4, input),
+            &mut out,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(out.flushed, vec![b"ab\nc".to_vec(), b"d\nef".to_vec()]);
+
+        let mut c = CatBuilder::new().with_line_buffered().build();
+        let mut out = FlushRecorder::default();
+        c.run(
+            &mut BufReader::with_capacity(2, input),
+            &mut out,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            out.flushed,
+            vec![b"ab\nc".to_vec(), b"d\n".to_vec(), b"ef".to_vec()]
+        );
+    }
+
+    #[test]
+    fn test_line_spans_inputs() {
+        // numbering and line state carry over between runs like GNU cat
+        let mut c = CatBuilder::new().with_number().with_show_ends().build();
+        let mut out: Vec<u8> = Vec::new();
+        c.run(&mut BufReader::new(&b"a\nb"[..]), &mut out, &mut Vec::new())
+            .unwrap();
+        c.run(
+            &mut BufReader::new(&b"c\r\n"[..]),
+            &mut out,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(out, b"     1\ta$\n     2\tbc^M$\n".to_vec());
+    }
+
+    #[test]
+    fn test_expand_operands() {
+        let data = PathBuf::from("ci-tests/test-data/cat_test.txt");
+
+        let c = CatBuilder::new().build();
+        assert_eq!(
+            c.expand_operands(&[PathBuf::from("ci-tests/test-data"), PathBuf::from("-")]),
+            vec![
+                Err("cat: ci-tests/test-data: Is a directory".to_string()),
+                Ok(PathBuf::from("-")),
+            ]
+        );
+        // without --glob the pattern is left to fail when opened
+        assert_eq!(
+            c.expand_operands(&[PathBuf::from("ci-tests/*/cat_test.txt")]),
+            vec![Ok(PathBuf::from("ci-tests/*/cat_test.txt"))]
+        );
+
+        let c = CatBuilder::new().with_recursive().build();
+        assert_eq!(
+            c.expand_operands(&[PathBuf::from("ci-tests/test-data")]),
+            vec![Ok(data.clone())]
+        );
+
+        let c = CatBuilder::new().with_glob().build();
+        assert_eq!(
+            c.expand_operands(&[PathBuf::from("ci-tests/*/cat_test.txt")]),
+            vec![Ok(data)]
+        );
+    }
+
+    #[test]
+    fn test_headers() {
+        let mut c = CatBuilder::new()
+            .with_headers()
+            .with_number()
+            .with_number_per_file()
+            .build();
+        let mut out: Vec<u8> = Vec::new();
+        for (name, contents) in [("a", &b"1\n2\n"[..]), ("-", &b"3"[..])].iter() {
+            c.start_file(Path::new(name), &mut out).unwrap();
+            c.run(&mut BufReader::new(*contents), &mut out, &mut Vec::new())
+                .unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "==> a <==\n     1\t1\n     2\t2\n\n==> standard input <==\n     1\t3"
+        );
+    }
+
+    #[test]
+    fn test_decompress() {
+        // `printf 'hello\n' | gzip -n`
+        let gzipped: &[u8] = &[
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xcb, 0x48, 0xcd, 0xc9,
+            0xc9, 0xe7, 0x02, 0x00, 0x20, 0x30, 0x3a, 0x36, 0x06, 0x00, 0x00, 0x00,
+        ];
+        let mut c = CatBuilder::new().with_decompress().with_number().build();
+        let mut out: Vec<u8> = Vec::new();
+        for input in [gzipped, &b"plain\n"[..], gzipped].iter() {
+            c.run(&mut BufReader::new(*input), &mut out, &mut Vec::new())
+                .unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "     1\thello\n     2\tplain\n     3\thello\n"
+        );
+
+        let mut c = CatBuilder::new().with_decompress().build();
+        let mut err: Vec<u8> = Vec::new();
+        assert!(c
+            .run(
+                &mut BufReader::new(&gzipped[..16]),
+                &mut Vec::new(),
+                &mut err
+            )
+            .is_err());
+        assert_eq!(
+            String::from_utf8(err).unwrap(),
+            "cat: -: unexpected end of compressed data\n"
+        );
+    }
+
+    #[test]
+    fn test_ranges() {
+        let input = b"one\n\nthree\nfour\nfive\n";
+
+        // numbers are those of the original lines
+        let mut c = CatBuilder::new()
+            .with_number()
+            .with_lines(Range::new(2, 3))
+            .build();
+        let mut out: Vec<u8> = Vec::new();
+        c.run(&mut BufReader::new(&input[..]), &mut out, &mut Vec::new())
+            .unwrap();
+        assert_eq!(out, b"     2\t\n     3\tthree\n".to_vec());
+
+        let mut c = CatBuilder::new()
+            .with_number_nonblank()
+            .with_lines(Range::new(3, 4))
+            .build();
+        let mut out: Vec<u8> = Vec::new();
+        c.run(&mut BufReader::new(&input[..]), &mut out, &mut Vec::new())
+            .unwrap();
+        assert_eq!(out, b"     2\tthree\n     3\tfour\n".to_vec());
+
+        // blank lines squeezed out of the selection take no number
+        let mut c = CatBuilder::new()
+            .with_number()
+            .with_squeeze_blank()
+            .with_lines(Range::new(3, 8))
+            .build();
+        let mut out: Vec<u8> = Vec::new();
+        c.run(
+            &mut BufReader::new(&b"a\n\n\n\nb\n\n\nc\n"[..]),
+            &mut out,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(out, b"     3\t\n     4\tb\n     5\t\n     6\tc\n".to_vec());
+
+        // reading stops once the range is exhausted
+        let mut c = CatBuilder::new().with_bytes(Range::new(2, 6)).build();
+        let mut reader = BufReader::with_capacity(4, &input[..]);
+        let mut out: Vec<u8> = Vec::new();
+        c.run(&mut reader, &mut out, &mut Vec::new()).unwrap();
+        assert_eq!(out, b"ne\n\nt".to_vec());
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"hree\nfour\nfive\n".to_vec());
+
+        // the byte range is applied by seeking when possible
+        let mut c = CatBuilder::new()
+            .with_show_ends()
+            .with_bytes(Range::new(12, 17))
+            .build();
+        let mut cursor = io::Cursor::new(&input[..]);
+        assert!(c.seek_input(&mut cursor).unwrap());
+        let mut out: Vec<u8> = Vec::new();
+        c.run(&mut BufReader::new(cursor), &mut out, &mut Vec::new())
+            .unwrap();
+        assert_eq!(out, b"four$\nf".to_vec());
+
+        let mut c = CatBuilder::new()
+            .with_number()
+            .with_bytes(Range::new(12, 17))
+            .build();
+        assert!(!c.seek_input(&mut io::Cursor::new(&input[..])).unwrap());
+    }
+
+    #[test]
+    fn test_json() {
+        let mut c = CatBuilder::new()
+            .with_json()
+            .with_number_nonblank()
+            .with_squeeze_blank()
+            .build();
+        let mut out: Vec<u8> = Vec::new();
+        c.start_file(Path::new("a.txt"), &mut out).unwrap();
+        c.run(
+            &mut BufReader::with_capacity(3, &b"x\"y\n\n\n\xfe\tz"[..]),
+            &mut out,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"file\":\"a.txt\",\"line\":1,\"offset\":0,\"number\":1,\"text\":\"x\\\"y\",\"newline\":true}\n\
+             {\"file\":\"a.txt\",\"line\":2,\"offset\":4,\"text\":\"\",\"newline\":true}\n\
+             {\"file\":\"a.txt\",\"line\":4,\"offset\":6,\"number\":2,\"text\":\"\u{fffd}\\tz\",\"text_bytes\":\"fe097a\",\"newline\":false}\n"
+        );
+    }
+
+    #[test]
+    fn test_html() {
+        let mut c = CatBuilder::new().with_html().with_show_all().build();
+        let mut out: Vec<u8> = Vec::new();
+        c.run(
+            &mut BufReader::new(&b"<a>\t&\x01\xbc\n"[..]),
+            &mut out,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert!(out.starts_with(HTML_STYLE));
+        assert_eq!(
+            String::from_utf8(out[HTML_STYLE.len()..].to_vec()).unwrap(),
+            "<pre class=\"cat\"><span class=\"line\" id=\"L1\">&lt;a&gt;\
+             <span class=\"tab\">^I</span>&amp;<span class=\"np\">^A</span>\
+             <span class=\"np\">M-&lt;</span><span class=\"eol\">$</span></span>\n</pre>\n"
+        );
+
+        // anchors follow the -b counter, the style is only written once
+        let mut c = CatBuilder::new().with_html().with_number_nonblank().build();
+        let mut out: Vec<u8> = Vec::new();
+        c.run(&mut BufReader::new(&b"a\n"[..]), &mut out, &mut Vec::new())
+            .unwrap();
+        out.clear();
+        c.run(&mut BufReader::new(&b"\nb"[..]), &mut out, &mut Vec::new())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "<pre class=\"cat\"><span class=\"line\"></span>\n\
+             <span class=\"line\" id=\"L2\"><a class=\"ln\" href=\"#L2\">     2</a>\tb</span></pre>\n"
+        );
+
+        // -H headers are escaped and come after the style, one per block
+        let mut c = CatBuilder::new().with_html().with_headers().build();
+        let mut out: Vec<u8> = Vec::new();
+        for name in ["<b>x&y", "-"].iter() {
+            c.start_file(Path::new(name), &mut out).unwrap();
+            c.run(&mut BufReader::new(&b"a\n"[..]), &mut out, &mut Vec::new())
+                .unwrap();
+        }
+        assert!(out.starts_with(HTML_STYLE));
+        assert_eq!(
+            String::from_utf8(out[HTML_STYLE.len()..].to_vec()).unwrap(),
+            "<div class=\"cat-file\">==&gt; &lt;b&gt;x&amp;y &lt;==</div>\n\
+             <pre class=\"cat\"><span class=\"line\" id=\"L1\">a</span>\n</pre>\n\
+             <div class=\"cat-file\">==&gt; standard input &lt;==</div>\n\
+             <pre class=\"cat\"><span class=\"line\" id=\"L2\">a</span>\n</pre>\n"
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut c = CatBuilder::new()
+            .with_squeeze_blank()
+            .with_stats(StatsFormat::Table)
+            .build();
+        let mut out: Vec<u8> = Vec::new();
+        for (name, contents) in [("a", &b"x\n\n\n\n\x01\t\xff"[..]), ("b", &b""[..])].iter() {
+            c.start_file(Path::new(name), &mut out).unwrap();
+            c.run(&mut BufReader::new(*contents), &mut out, &mut Vec::new())
+                .unwrap();
+        }
+        // stdout is unchanged
+        assert_eq!(out, b"x\n\n\x01\t\xff".to_vec());
+        assert_eq!(
+            c.stats(),
+            &[
+                (
+                    PathBuf::from("a"),
+                    Stats {
+                        lines: 5,
+                        bytes: 8,
+                        squeezed: 2,
+                        nonprinting: 2,
+                        unterminated: 1,
+                    }
+                ),
+                (PathBuf::from("b"), Stats::default()),
+            ][..]
+        );
+
+        let mut err: Vec<u8> = Vec::new();
+        c.report(&mut err).unwrap();
+        assert_eq!(
+            String::from_utf8(err).unwrap(),
+            "     lines        bytes  squeezed  nonprinting unterminated file\n\
+             \x20        5            8         2            2            1 a\n\
+             \x20        0            0         0            0            0 b\n\
+             \x20        5            8         2            2            1 total\n"
+        );
+
+        c.option.stats = Some(StatsFormat::Json);
+        let mut err: Vec<u8> = Vec::new();
+        c.report(&mut err).unwrap();
+        assert!(String::from_utf8(err).unwrap().ends_with(
+            "\"total\":{\"lines\":5,\"bytes\":8,\"squeezed\":2,\"nonprinting\":2,\"unterminated\":1}}\n"
+        ));
+    }
+
+    #[test]
+    fn test_timestamp() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        fn clock() -> SystemTime {
+            UNIX_EPOCH + Duration::from_secs(1_234_567_890)
+        }
+
+        let mut c = CatBuilder::new()
+            .with_timestamp(TimestampMode::Relative)
+            .with_number()
+            .with_show_ends()
+            .build();
+        c.clock = clock;
+        c.started = clock() - Duration::from_secs(62);
+        let mut out: Vec<u8> = Vec::new();
+        c.run(
+            &mut BufReader::new(&b"a\nb\n"[..]),
+            &mut out,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "00:01:02      1\ta$\n00:01:02      2\tb$\n"
+        );
+
+        let mut c = CatBuilder::new()
+            .with_timestamp(TimestampMode::Delta)
+            .with_timestamp_format("+%s")
+            .with_squeeze_blank()
+            .build();
+        c.clock = clock;
+        c.started = clock();
+        let mut out: Vec<u8> = Vec::new();
+        c.run(
+            &mut BufReader::new(&b"\n\n\nc"[..]),
+            &mut out,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "+0 \n+0 c");
+    }
+
+    #[test]
+    fn test_resume() {
+        use std::io::Cursor;
+
+        let log = Path::new("app.log");
+        let mut c = CatBuilder::new().with_number().build();
+        let mut out: Vec<u8> = Vec::new();
+        let mut input = Cursor::new(b"one\ntwo\npar".to_vec());
+        assert_eq!(c.resume_input(log, &mut input).unwrap(), 0);
+        c.start_file(log, &mut out).unwrap();
+        c.run(&mut BufReader::new(input), &mut out, &mut Vec::new())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "     1\tone\n     2\ttwo\n     3\tpar"
+        );
+        let state = c.state();
+        assert_eq!(
+            state,
+            CatState {
+                number: 4,
+                blank: 0,
+                at_line_start: false,
+                offsets: vec![(log.to_path_buf(), 11)],
+            }
+        );
+
+        // a new process picks up the grown file after the saved state
+        let state: CatState = state.to_string().parse().unwrap();
+        let mut c = CatBuilder::new().with_number().resume(&state);
+        let mut out: Vec<u8> = Vec::new();
+        let mut input = Cursor::new(b"one\ntwo\npartial\nthree\n".to_vec());
+        assert_eq!(c.resume_input(log, &mut input).unwrap(), 11);
+        c.start_file(log, &mut out).unwrap();
+        c.run(&mut BufReader::new(input), &mut out, &mut Vec::new())
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "tial\n     4\tthree\n");
+        assert_eq!(c.state().offsets, vec![(log.to_path_buf(), 22)]);
+        // the same file later in the run is read from the start
+        let mut input = Cursor::new(b"one\ntwo\npartial\nthree\n".to_vec());
+        assert_eq!(c.resume_input(log, &mut input).unwrap(), 0);
+
+        // a truncated file is read again from the start
+        let state = c.state();
+        let mut c = CatBuilder::new().with_number().resume(&state);
+        let mut out: Vec<u8> = Vec::new();
+        let mut input = Cursor::new(b"new\n".to_vec());
+        assert_eq!(c.resume_input(log, &mut input).unwrap(), 0);
+        c.start_file(log, &mut out).unwrap();
+        c.run(&mut BufReader::new(input), &mut out, &mut Vec::new())
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "     5\tnew\n");
+    }
+
+    #[test]
+    fn test_follow() {
+        let dir = std::env::temp_dir().join(format!("rtw-follow-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.log");
+        fs::write(&path, b"one\ntw").unwrap();
+
+        let mut c = CatBuilder::new().with_number().build();
+        let mut out: Vec<u8> = Vec::new();
+        let mut err: Vec<u8> = Vec::new();
+        let mut step = 0;
+        c.start_file(&path, &mut out).unwrap();
+        c.follow(&path, &mut out, &mut err, |c| {
+            step += 1;
+            match step {
+                // append, finishing the partial line
+                1 => {
+                    assert_eq!(c.state().offset(&path), 6);
+                    let mut f = fs::OpenOptions::new().append(true).open(&path).unwrap();
+                    f.write_all(b"o\nthree\n").unwrap();
+                }
+                // truncate
+                2 => fs::write(&path, b"four\n").unwrap(),
+                // rotate
+                3 => {
+                    fs::rename(&path, dir.join("app.log.1")).unwrap();
+                    fs::write(&path, b"five\n").unwrap();
+                }
+                _ => return false,
+            }
+            true
+        })
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "     1\tone\n     2\ttwo\n     3\tthree\n     4\tfour\n     5\tfive\n"
+        );
+        let err = String::from_utf8(err).unwrap();
+        assert!(err.contains("app.log: file truncated\n"));
+        assert!(err.contains("app.log' has been replaced; following new file\n"));
+        assert_eq!(c.state().offset(&path), 5);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_follow_operand() {
+        let dir = std::env::temp_dir().join(format!("rtw-follow-operand-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("app.log");
+        fs::write(&log, b"one\n").unwrap();
+        let elf = dir.join("a.out");
+        fs::write(&elf, b"\x7fELF\x02\x01\x01\x00\n").unwrap();
+        let call = |args: &[&OsStr]| {
+            let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+            let mut stdin = &b""[..];
+            let mut stdout: Vec<u8> = Vec::new();
+            let mut stderr: Vec<u8> = Vec::new();
+            let status = {
+                let mut ctx = Context::new(&mut stdin, &mut stdout, &mut stderr);
+                CatBuilder::new().build().call(&args, &mut ctx)
+            };
+            (
+                status,
+                String::from_utf8(stdout).unwrap(),
+                String::from_utf8(stderr).unwrap(),
+            )
+        };
+
+        // a state that cannot be saved stops following, and is reported once
+        let state = dir.join("none").join("state");
+        let mut state_arg = OsString::from("--state=");
+        state_arg.push(&state);
+        assert_eq!(
+            call(&[OsStr::new("--follow"), &state_arg, log.as_os_str()]),
+            (
+                1,
+                "one\n".to_string(),
+                format!("cat: {}: No such file or directory\n", state.display())
+            )
+        );
+
+        // --binary is applied to a followed file as to any other
+        assert_eq!(
+            call(&[
+                OsStr::new("--follow"),
+                OsStr::new("--binary=refuse"),
+                elf.as_os_str()
+            ]),
+            (
+                1,
+                String::new(),
+                format!("cat: {}: binary file (use -v or --force)\n", elf.display())
+            )
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_bsd_profile() {
+        // expected/NAME.out is `cat -NAME a.txt b.txt` ("plain": no options)
+        let dir = Path::new("ci-tests/bsd");
+        let mut fixtures: Vec<PathBuf> = fs::read_dir(dir.join("expected"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        fixtures.sort();
+        assert!(!fixtures.is_empty());
+        for fixture in fixtures {
+            let name = fixture.file_stem().unwrap().to_str().unwrap();
+            let mut c = CatBuilder::new().with_profile(Profile::Bsd).build();
+            if name != "plain" {
+                c.parse([format!("-{}", name)]).unwrap();
+            }
+            let mut out: Vec<u8> = Vec::new();
+            for input in ["a.txt", "b.txt"].iter() {
+                let path = dir.join(input);
+                c.start_file(&path, &mut out).unwrap();
+                let mut f = BufReader::new(File::open(&path).unwrap());
+                c.run(&mut f, &mut out, &mut Vec::new()).unwrap();
+            }
+            assert_eq!(out, fs::read(&fixture).unwrap(), "cat -{}", name);
+        }
+    }
+
+    #[test]
+    fn test_binary() {
+        assert!(!looks_binary(b""));
+        assert!(!looks_binary(b"plain text\twith tabs\r\n"));
+        assert!(!looks_binary("UTF-8 text, ünïcödé".as_bytes()));
+        assert!(!looks_binary(
+            "cut at the end \u{20ac}".as_bytes().split_last().unwrap().1
+        ));
+        assert!(!looks_binary(b"\x1b[1mbold\x1b[0m and one \x01 control"));
+        assert!(looks_binary(b"ELF\x00\x01"));
+        assert!(looks_binary(b"\x89PNG\r\n\x1a\n\xfe\xed\xfa\xce"));
+
+        let elf: &[u8] = b"\x7fELF\x02\x01\x01\x00\n";
+        let mut c = CatBuilder::new().with_binary(BinaryAction::Refuse).build();
+        let mut out: Vec<u8> = Vec::new();
+        let mut err: Vec<u8> = Vec::new();
+        for (name, input) in [("a.out", elf), ("notes", &b"text\n"[..])].iter() {
+            c.start_file(Path::new(name), &mut out).unwrap();
+            c.run(&mut BufReader::new(*input), &mut out, &mut err)
+                .unwrap();
+        }
+        assert_eq!(out, b"text\n".to_vec());
+        assert_eq!(
+            String::from_utf8(err).unwrap(),
+            "cat: a.out: binary file (use -v or --force)\n"
+        );
+        assert!(c.failed());
+
+        // -v and --force both let it through
+        for builder in [
+            CatBuilder::new()
+                .with_binary(BinaryAction::Refuse)
+                .with_show_nonprinting(),
+            CatBuilder::new()
+                .with_binary(BinaryAction::Refuse)
+                .with_force(),
+        ]
+        .iter()
+        {
+            let mut c = builder.build();
+            let mut out: Vec<u8> = Vec::new();
+            c.run(&mut BufReader::new(elf), &mut out, &mut Vec::new())
+                .unwrap();
+            assert!(!out.is_empty());
+            assert!(!c.failed());
+        }
+
+        // shown as with -v on a terminal, only for the binary file
+        let mut c = CatBuilder::new().with_binary(BinaryAction::Show).build();
+        c.set_terminal_output(true);
+        let mut out: Vec<u8> = Vec::new();
+        for input in [elf, &b"bell \x07\n"[..]].iter() {
+            c.run(&mut BufReader::new(*input), &mut out, &mut Vec::new())
+                .unwrap();
+        }
+        assert_eq!(out, b"^?ELF^B^A^A^@\nbell \x07\n".to_vec());
+
+        // and passed through elsewhere
+        let mut c = CatBuilder::new().with_binary(BinaryAction::Show).build();
+        let mut out: Vec<u8> = Vec::new();
+        c.run(&mut BufReader::new(elf), &mut out, &mut Vec::new())
+            .unwrap();
+        assert_eq!(out, elf.to_vec());
+    }
+
+    #[test]
+    fn test_color() {
+        let input: &[u8] = b"a\tb\x01\n";
+        let colored = |c: &mut Cat| {
+            let mut out: Vec<u8> = Vec::new();
+            c.run(&mut BufReader::new(input), &mut out, &mut Vec::new())
+                .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let mut c = CatBuilder::new()
+            .with_show_all()
+            .with_number()
+            .with_color(ColorWhen::Always)
+            .build();
+        assert_eq!(
+            colored(&mut c),
+            "\x1b[32m     1\x1b[0m\ta\x1b[90m^I\x1b[0mb\x1b[1;31m^A\x1b[0m\x1b[90m$\x1b[0m\n"
+        );
+
+        // auto: only on terminals, and not with NO_COLOR
+        let mut c = CatBuilder::new()
+            .with_show_all()
+            .with_color(ColorWhen::Auto)
+            .build();
+        assert_eq!(colored(&mut c), "a^Ib^A$\n");
+        c.set_terminal_output(true);
+        c.set_color_env(None, Some(OsStr::new("np=7:tab=:eol=")));
+        assert_eq!(colored(&mut c), "a^Ib\x1b[7m^A\x1b[0m$\n");
+        c.set_color_env(Some(OsStr::new("1")), None);
+        assert_eq!(colored(&mut c), "a^Ib^A$\n");
+        // an empty NO_COLOR does not count
+        c.set_color_env(Some(OsStr::new("")), None);
+        assert!(colored(&mut c).contains('\x1b'));
+
+        // --color=always wins over NO_COLOR
+        let mut c = CatBuilder::new()
+            .with_show_tabs()
+            .with_color(ColorWhen::Always)
+            .build();
+        c.set_color_env(Some(OsStr::new("1")), None);
+        assert_eq!(colored(&mut c), "a\x1b[90m^I\x1b[0mb\x01\n");
+    }
+
+    #[test]
+    fn test_utility() {
+        let args: Vec<OsString> = ["-n", "-", "ci-tests/none.txt", "-"]
+            .iter()
+            .map(OsString::from)
+            .collect();
+        let mut stdin = &b"from stdin\n"[..];
+        let mut stdout: Vec<u8> = Vec::new();
+        let mut stderr: Vec<u8> = Vec::new();
+        let mut c = CatBuilder::new().build();
+        assert_eq!(c.name(), "cat");
+        let status = {
+            let mut ctx = Context::new(&mut stdin, &mut stdout, &mut stderr);
+            c.call(&args, &mut ctx)
+        };
+        assert_eq!(status, 1);
+        assert_eq!(String::from_utf8(stdout).unwrap(), "     1\tfrom stdin\n");
+        assert_eq!(
+            String::from_utf8(stderr).unwrap(),
+            "cat: ci-tests/none.txt: No such file or directory\n"
+        );
+
+        // the environment comes from the context too
+        let mut stdout: Vec<u8> = Vec::new();
+        let mut stderr: Vec<u8> = Vec::new();
+        let mut c = CatBuilder::new().build();
+        let status = {
+            let mut stdin = &b"\t\n"[..];
+            let mut ctx = Context::new(&mut stdin, &mut stdout, &mut stderr);
+            ctx.stdout_is_terminal = true;
+            ctx.env.push(("RTW_CAT_COLORS".into(), "tab=4".into()));
+            c.call(&["-T".into(), "--color=auto".into()], &mut ctx)
+        };
+        assert_eq!(status, 0);
+        assert_eq!(stdout, b"\x1b[4m^I\x1b[0m\n".to_vec());
+
+        let mut stdout: Vec<u8> = Vec::new();
+        let mut stderr: Vec<u8> = Vec::new();
+        let status = {
+            let mut stdin = &b""[..];
+            let mut ctx = Context::new(&mut stdin, &mut stdout, &mut stderr);
+            CatBuilder::new().build().call(&["-Q".into()], &mut ctx)
+        };
+        assert_eq!(status, 1);
+        assert!(stdout.is_empty());
+        assert!(String::from_utf8(stderr)
+            .unwrap()
+            .starts_with("cat: invalid option -- 'Q'\n"));
+    }
+
+    #[test]
+    fn test_repeated_operand() {
+        // a file given twice is read in full both times
+        const TEST_DATA_PATH: &str = "ci-tests/test-data/cat_test.txt";
+        let data = std::fs::read_to_string(TEST_DATA_PATH).unwrap();
+        let numbered: String = data
+            .split_inclusive('\n')
+            .chain(data.split_inclusive('\n'))
+            .enumerate()
+            .map(|(i, line)| format!("{:>6}\t{}", i + 1, line))
+            .collect();
+        let cases: &[(&[&str], String)] = &[
+            (&[TEST_DATA_PATH, TEST_DATA_PATH], data.repeat(2)),
+            (&["-n", TEST_DATA_PATH, TEST_DATA_PATH], numbered),
+        ];
+        for (args, expects) in cases.iter() {
+            let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+            let mut stdin = &b""[..];
+            let mut stdout: Vec<u8> = Vec::new();
+            let mut stderr: Vec<u8> = Vec::new();
+            let status = {
+                let mut ctx = Context::new(&mut stdin, &mut stdout, &mut stderr);
+                CatBuilder::new().build().call(&args, &mut ctx)
+            };
+            assert_eq!(status, 0);
+            assert_eq!(String::from_utf8(stdout).unwrap(), *expects);
+        }
+    }
+
+    /// Run cat with `args` over the files of `fs`.
+    fn call_in(fs: &MemoryFs, args: &[&str]) -> (i32, String, String) {
+        let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+        let mut stdin = &b"from stdin\n"[..];
+        let mut stdout: Vec<u8> = Vec::new();
+        let mut stderr: Vec<u8> = Vec::new();
+        let status = {
+            let mut ctx = Context::new(&mut stdin, &mut stdout, &mut stderr);
+            ctx.fs = fs;
+            CatBuilder::new().build().call(&args, &mut ctx)
+        };
+        (
+            status,
+            String::from_utf8(stdout).unwrap(),
+            String::from_utf8(stderr).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_outside_memory_fs() {
+        let mut fs = MemoryFs::new();
+        fs.with_file("a", b"one\n")
+            .with_file("rtw-memory-only-1", b"two\n")
+            .with_dir("rtw-memory-only-dir")
+            .with_file("rtw-memory-only-dir/b", b"three\n");
+
+        // a file held in memory cannot be watched, so it is read once
+        assert_eq!(
+            call_in(&fs, &["--follow", "a"]),
+            (0, "one\n".to_string(), String::new())
+        );
+
+        // wildcards and recursion list the real directories
+        assert_eq!(
+            call_in(&fs, &["--glob", "rtw-memory-only-*"]),
+            (
+                1,
+                String::new(),
+                "cat: rtw-memory-only-*: No such file or directory\n".to_string()
+            )
+        );
+        assert_eq!(
+            call_in(&fs, &["-r", "rtw-memory-only-dir"]),
+            (
+                1,
+                String::new(),
+                "cat: rtw-memory-only-dir: No such file or directory\n".to_string()
+            )
+        );
+
+        // the state is kept on disk, and a file in memory is read from the start
+        let dir = std::env::temp_dir().join(format!("rtw-memory-state-{}", process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let state = format!("--state={}", dir.join("state").display());
+        for _ in 0..2 {
+            assert_eq!(
+                call_in(&fs, &[state.as_str(), "a"]),
+                (0, "one\n".to_string(), String::new())
+            );
+            assert!(dir.join("state").is_file());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_errors() {
+        let mut fs = MemoryFs::new();
+        fs.with_file("a", b"one\ntwo\n")
+            .with_file("secret", b"hidden\n")
+            .with_denied("secret")
+            .with_dir("dir")
+            .with_file("slow", b"three\nfour\n")
+            .with_short_reads("slow", 1)
+            .with_file("bad", b"five\nsix\n")
+            .with_error_at("bad", 5);
+
+        // missing, unreadable and directory operands are skipped
+        assert_eq!(
+            call_in(&fs, &["-n", "a", "none", "secret", "dir", "-", "a"]),
+            (
+                1,
+                "     1\tone\n     2\ttwo\n     3\tfrom stdin\n     4\tone\n     5\ttwo\n"
+                    .to_string(),
+                "cat: none: No such file or directory\n\
+                 cat: secret: Permission denied\n\
+                 cat: dir: Is a directory\n"
+                    .to_string()
+            )
+        );
+
+        // a byte at a time reads the same
+        assert_eq!(
+            call_in(&fs, &["-nE", "slow", "a"]),
+            (
+                0,
+                "     1\tthree$\n     2\tfour$\n     3\tone$\n     4\ttwo$\n".to_string(),
+                String::new()
+            )
+        );
+
+        // what was read before the error is still written, then cat stops
+        assert_eq!(
+            call_in(&fs, &["a", "bad", "a"]),
+            (
+                1,
+                "one\ntwo\nfive\n".to_string(),
+                "cat: bad: Input/output error\n".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_decompress_errors() {
+        let mut fs = MemoryFs::new();
+        fs.with_file("a", b"one\n")
+            .with_file("short.gz", b"\x1f\x8b\x08\x00\x00\x00")
+            .with_file("data.xz", b"\xfd7zXZ\x00\x00");
+        assert_eq!(
+            call_in(&fs, &["-z", "short.gz"]),
+            (
+                1,
+                String::new(),
+                "cat: short.gz: unexpected end of compressed data\n".to_string()
+            )
+        );
+        if !cfg!(feature = "xz") {
+            assert_eq!(
+                call_in(&fs, &["-z", "data.xz"]),
+                (
+                    1,
+                    String::new(),
+                    "cat: data.xz: xz compressed input is not supported \
+                     (build with the 'xz' feature)\n"
+                        .to_string()
+                )
+            );
+        }
+    }
+
+    /// A standard output which takes `room` bytes and then fails.
+    struct Full {
+        room: usize,
+    }
+
+    impl Write for Full {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.room == 0 {
+                return Err(io::Error::other("No space left on device"));
+            }
+            let n = buf.len().min(self.room);
+            self.room -= n;
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_stats_on_errors() {
+        let mut fs = MemoryFs::new();
+        fs.with_file("a", b"one\ntwo\n").with_file("b", b"three\n");
+        let report = "     lines        bytes  squeezed  nonprinting unterminated file\n\
+                      \x20        2            8         0            0            0 a\n\
+                      \x20        2            8         0            0            0 total\n";
+
+        // a missing operand is reported, then the report of the rest
+        assert_eq!(
+            call_in(&fs, &["--stats", "none", "a"]),
+            (
+                1,
+                "one\ntwo\n".to_string(),
+                format!("cat: none: No such file or directory\n{}", report)
+            )
+        );
+
+        // catting stops at a write error, and the report still follows
+        let args: Vec<OsString> = ["--stats", "a", "b"].iter().map(OsString::from).collect();
+        let mut stdin = &b""[..];
+        let mut stdout = Full { room: 8 };
+        let mut stderr: Vec<u8> = Vec::new();
+        let status = {
+            let mut ctx = Context::new(&mut stdin, &mut stdout, &mut stderr);
+            ctx.fs = &fs;
+            CatBuilder::new().build().call(&args, &mut ctx)
+        };
+        assert_eq!(status, 1);
+        assert_eq!(
+            String::from_utf8(stderr).unwrap(),
+            format!("cat: write error: No space left on device\n{}", report)
+        );
+    }
+
+    #[test]
+    fn test_trailing_cr_write_error() {
+        // the "\r" held back by -E is written at the end of the file
+        let mut c = CatBuilder::new().with_show_ends().build();
+        let mut stderr: Vec<u8> = Vec::new();
+        assert!(c
+            .run(
+                &mut BufReader::new(&b"a\r"[..]),
+                &mut Full { room: 1 },
+                &mut stderr
+            )
+            .is_err());
+        assert_eq!(
+            stderr,
+            b"cat: write error: No space left on device\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_input_is_output() {
+        let mut fs = MemoryFs::new();
+        fs.with_file("a", b"one\n")
+            .with_file("log", b"old\n")
+            .with_file("empty", b"")
+            .with_stdout("log");
+        assert_eq!(
+            call_in(&fs, &["a", "log", "a"]),
+            (
+                1,
+                "one\none\n".to_string(),
+                "cat: log: input file is output file\n".to_string()
+            )
+        );
+
+        // an empty output file has nothing to read back
+        fs.with_stdout("empty");
+        assert_eq!(
+            call_in(&fs, &["empty", "a"]),
+            (0, "one\n".to_string(), String::new())
+        );
+    }
 }
@@ -1,15 +1,35 @@
 #![allow(dead_code)]
+use std::borrow::Cow;
 use std::ffi::OsStr;
 
+/// The bytes of `s`: exactly those on Unix, elsewhere its UTF-8 with
+/// anything invalid replaced.
+#[cfg(unix)]
+pub(crate) fn os_bytes(s: &OsStr) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(s.as_bytes())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn os_bytes(s: &OsStr) -> Cow<'_, [u8]> {
+    match s.to_string_lossy() {
+        Cow::Borrowed(s) => Cow::Borrowed(s.as_bytes()),
+        Cow::Owned(s) => Cow::Owned(s.into_bytes()),
+    }
+}
+
+// Arguments need not be UTF-8; anything not valid UTF-8 is never an
+// option name, so it is looked at lossily.
+
 pub fn is_option(arg: &OsStr) -> bool {
-    arg.len() > 1 && arg.to_str().unwrap().chars().nth(0) == Some('-')
+    arg.len() > 1 && arg.to_string_lossy().starts_with('-')
 }
 
 pub fn is_singlechar_option(arg: &OsStr) -> bool {
-    arg.len() > 1 && arg.to_str().unwrap().chars().nth(0) == Some('-')
-        && arg.to_str().unwrap().chars().nth(1) != Some('-')
+    let arg = arg.to_string_lossy();
+    arg.len() > 1 && arg.starts_with('-') && arg.chars().nth(1) != Some('-')
 }
 
 pub fn is_multichar_option(arg: &OsStr) -> bool {
-    arg.to_str().unwrap().starts_with("--")
+    arg.to_string_lossy().starts_with("--")
 }
@@ -0,0 +1,121 @@
+//! ANSI colours for `cat --color`.
+
+/// When `--color` highlights the output.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum ColorWhen {
+    /// only when writing to a terminal and `NO_COLOR` is not set
+    Auto,
+    Always,
+    #[default]
+    Never,
+}
+
+/// SGR parameters for each kind of highlighted output, as set by the
+/// `RTW_CAT_COLORS` environment variable, e.g. `np=1;31:eol=90:ln=32`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Palette {
+    /// `^X` and `M-` notation for nonprinting bytes
+    pub np: String,
+    /// `^I`
+    pub tab: String,
+    /// `$`
+    pub eol: String,
+    /// line numbers
+    pub ln: String,
+    /// timestamps
+    pub ts: String,
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette {
+            np: "1;31".to_string(),
+            tab: "90".to_string(),
+            eol: "90".to_string(),
+            ln: "32".to_string(),
+            ts: "36".to_string(),
+        }
+    }
+}
+
+impl Palette {
+    /// The default palette with the `key=SGR` entries of `spec` applied.
+    /// Entries with an unknown key or a malformed value are ignored, as
+    /// GREP_COLORS does; an empty value turns that highlight off.
+    pub fn parse(spec: &str) -> Palette {
+        let mut palette = Palette::default();
+        for entry in spec.split(':') {
+            let (key, sgr) = match entry.split_once('=') {
+                Some(pair) => pair,
+                None => continue,
+            };
+            if !sgr.bytes().all(|b| b.is_ascii_digit() || b == b';') {
+                continue;
+            }
+            let slot = match key {
+                "np" => &mut palette.np,
+                "tab" => &mut palette.tab,
+                "eol" => &mut palette.eol,
+                "ln" => &mut palette.ln,
+                "ts" => &mut palette.ts,
+                _ => continue,
+            };
+            *slot = sgr.to_string();
+        }
+        palette
+    }
+
+    fn sgr(&self, class: &str) -> &str {
+        match class {
+            "np" => &self.np,
+            "tab" => &self.tab,
+            "eol" => &self.eol,
+            "ln" => &self.ln,
+            "ts" => &self.ts,
+            _ => "",
+        }
+    }
+
+    /// Append `text` to `out` in the colour for `class`.
+    pub fn paint(&self, out: &mut Vec<u8>, class: &str, text: &[u8]) {
+        let sgr = self.sgr(class);
+        if sgr.is_empty() {
+            out.extend_from_slice(text);
+            return;
+        }
+        out.extend_from_slice(b"\x1b[");
+        out.extend_from_slice(sgr.as_bytes());
+        out.push(b'm');
+        out.extend_from_slice(text);
+        out.extend_from_slice(b"\x1b[0m");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Palette::parse(""), Palette::default());
+        let palette = Palette::parse("np=7:eol=:ln=1;33:bogus=1:tab=red:ts");
+        assert_eq!(
+            palette,
+            Palette {
+                np: "7".to_string(),
+                eol: String::new(),
+                ln: "1;33".to_string(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_paint() {
+        let palette = Palette::parse("eol=");
+        let mut out = Vec::new();
+        palette.paint(&mut out, "np", b"M-^@");
+        palette.paint(&mut out, "eol", b"$");
+        assert_eq!(out, b"\x1b[1;31mM-^@\x1b[0m$".to_vec());
+    }
+}
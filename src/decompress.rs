@@ -0,0 +1,206 @@
+//! Recognise compressed input by its magic bytes and decode it on the fly.
+use super::inflate::GzDecoder;
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Plain,
+    Gzip,
+    Xz,
+    Zstd,
+    Bzip2,
+}
+
+const MAGIC_LEN: usize = 6;
+
+/// Identify the compression format from the first bytes of a stream.
+pub fn sniff(head: &[u8]) -> Format {
+    if head.starts_with(&[0x1f, 0x8b]) {
+        Format::Gzip
+    } else if head.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        Format::Xz
+    } else if head.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Format::Zstd
+    } else if head.len() >= 4 && head.starts_with(b"BZh") && (b'1'..=b'9').contains(&head[3]) {
+        Format::Bzip2
+    } else {
+        Format::Plain
+    }
+}
+
+#[allow(dead_code)]
+fn unsupported(format: &str, feature: &str) -> io::Error {
+    io::Error::other(format!(
+        "{} compressed input is not supported (build with the '{}' feature)",
+        format, feature
+    ))
+}
+
+/// Whether `head` could still turn out to start a magic number once more
+/// bytes arrive.
+fn incomplete(head: &[u8]) -> bool {
+    const MAGICS: &[&[u8]] = &[
+        &[0x1f, 0x8b],
+        &[0xfd, b'7', b'z', b'X', b'Z', 0x00],
+        &[0x28, 0xb5, 0x2f, 0xfd],
+        // any block size digit will do; only the length matters here
+        b"BZh9",
+    ];
+    MAGICS
+        .iter()
+        .any(|magic| head.len() < magic.len() && magic.starts_with(head))
+}
+
+/// Wrap `input` so that reading it yields the decompressed contents.
+/// Input in an unknown format is passed through unchanged.
+pub fn reader<'a, R: BufRead + 'a>(mut input: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    // take the magic from what has already arrived, waiting for more only
+    // while it may still be one, so that plain text from a pipe or
+    // terminal is not held back; then put it back in front of the stream
+    let mut head = Vec::with_capacity(MAGIC_LEN);
+    while incomplete(&head) {
+        let chunk = input.fill_buf()?;
+        if chunk.is_empty() {
+            break;
+        }
+        let n = chunk.len().min(MAGIC_LEN - head.len());
+        head.extend_from_slice(&chunk[..n]);
+        input.consume(n);
+    }
+    let format = sniff(&head);
+    let input = Cursor::new(head).chain(input);
+    Ok(match format {
+        Format::Plain => Box::new(BufReader::new(input)),
+        Format::Gzip => Box::new(BufReader::new(GzDecoder::new(input))),
+        Format::Xz => xz(input)?,
+        Format::Zstd => zstd(input)?,
+        Format::Bzip2 => bzip2(input)?,
+    })
+}
+
+#[cfg(feature = "xz")]
+fn xz<'a, R: Read + 'a>(input: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    // lzma-rs has no streaming xz reader, so decode the whole stream
+    let mut decoded = Vec::new();
+    lzma_rs::xz_decompress(&mut BufReader::new(input), &mut decoded)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err)))?;
+    Ok(Box::new(Cursor::new(decoded)))
+}
+
+#[cfg(not(feature = "xz"))]
+fn xz<'a, R: Read + 'a>(_input: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    Err(unsupported("xz", "xz"))
+}
+
+#[cfg(feature = "zstd")]
+fn zstd<'a, R: Read + 'a>(input: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    let decoder = ruzstd::decoding::StreamingDecoder::new(input)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+    Ok(Box::new(BufReader::new(decoder)))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd<'a, R: Read + 'a>(_input: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    Err(unsupported("zstd", "zstd"))
+}
+
+#[cfg(feature = "bzip2")]
+fn bzip2<'a, R: Read + 'a>(input: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    Ok(Box::new(BufReader::new(bzip2_rs::DecoderReader::new(
+        input,
+    ))))
+}
+
+#[cfg(not(feature = "bzip2"))]
+fn bzip2<'a, R: Read + 'a>(_input: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    Err(unsupported("bzip2", "bzip2"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(sniff(b"\x1f\x8b\x08\x00"), Format::Gzip);
+        assert_eq!(sniff(b"\xfd7zXZ\x00"), Format::Xz);
+        assert_eq!(sniff(b"\x28\xb5\x2f\xfd"), Format::Zstd);
+        assert_eq!(sniff(b"BZh9"), Format::Bzip2);
+        assert_eq!(sniff(b"BZh plain"), Format::Plain);
+        assert_eq!(sniff(b"\x1f"), Format::Plain);
+        assert_eq!(sniff(b""), Format::Plain);
+    }
+
+    #[test]
+    fn test_reader_passes_plain_through() {
+        let mut out = Vec::new();
+        reader(&b"abc"[..]).unwrap().read_to_end(&mut out).unwrap();
+        assert_eq!(out, b"abc".to_vec());
+    }
+
+    /// A pipe that has delivered `data` and has nothing more yet.
+    struct Waiting<'a> {
+        data: &'a [u8],
+    }
+
+    impl<'a> Read for Waiting<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.fill_buf()?.len().min(buf.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.consume(n);
+            Ok(n)
+        }
+    }
+
+    impl<'a> BufRead for Waiting<'a> {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            if self.data.is_empty() {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            Ok(self.data)
+        }
+
+        fn consume(&mut self, amt: usize) {
+            self.data = &self.data[amt..];
+        }
+    }
+
+    #[test]
+    fn test_reader_does_not_wait_for_the_magic() {
+        // two bytes of text are told from any magic number at once
+        let mut decoded = reader(Waiting { data: b"ab" }).unwrap();
+        let mut buf = [0u8; 16];
+        assert_eq!(decoded.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"ab");
+        assert_eq!(
+            decoded.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+
+        // the start of one is waited on
+        assert_eq!(
+            reader(Waiting { data: b"BZ" }).err().unwrap().kind(),
+            io::ErrorKind::WouldBlock
+        );
+        assert_eq!(
+            reader(Waiting { data: b"BZx" })
+                .unwrap()
+                .fill_buf()
+                .unwrap(),
+            b"BZx"
+        );
+
+        // bzip2 takes the block size digit after "BZh" to tell
+        assert_eq!(
+            reader(Waiting { data: b"BZh" }).err().unwrap().kind(),
+            io::ErrorKind::WouldBlock
+        );
+        assert_eq!(
+            reader(Waiting { data: b"BZhx" })
+                .unwrap()
+                .fill_buf()
+                .unwrap(),
+            b"BZhx"
+        );
+    }
+}
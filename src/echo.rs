@@ -0,0 +1,546 @@
+#![allow(dead_code)]
+use super::cat::describe;
+use super::cliopt;
+use super::utility::{Context, Utility};
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Write};
+
+#[derive(Clone, Default, PartialEq, Debug)]
+struct EchoOption {
+    no_newline: bool,
+    escapes: bool,
+    /// POSIX rules: no options unless the first argument is `-n`, and
+    /// escapes always interpreted
+    posixly_correct: bool,
+    display_help: bool,
+    display_version: bool,
+}
+
+pub struct EchoBuilder {
+    option: EchoOption,
+}
+
+pub struct Echo {
+    option: EchoOption,
+    /// the arguments given to `parse_args`, scanned in `execute` once the
+    /// environment is known
+    args: Vec<OsString>,
+}
+
+impl Default for EchoBuilder {
+    fn default() -> EchoBuilder {
+        EchoBuilder::new()
+    }
+}
+
+impl EchoBuilder {
+    pub fn new() -> EchoBuilder {
+        EchoBuilder {
+            option: EchoOption::default(),
+        }
+    }
+    pub fn with_escapes(&mut self) -> &mut Self {
+        self.option.escapes = true;
+        self
+    }
+    pub fn with_no_newline(&mut self) -> &mut Self {
+        self.option.no_newline = true;
+        self
+    }
+    pub fn with_posixly_correct(&mut self) -> &mut Self {
+        self.option.posixly_correct = true;
+        self
+    }
+    pub fn build(&self) -> Echo {
+        Echo {
+            option: self.option.clone(),
+            args: Vec::new(),
+        }
+    }
+}
+
+/// Decode the backslash escape at the start of `s`, the backslash itself
+/// already taken, appending the result to `out`. Returns how many bytes of
+/// `s` it used, or `None` for `\c`, after which nothing more is output. A
+/// backslash that starts no escape is kept and uses nothing.
+///
+/// Octal escapes are `\NNN`; when `zero_octal` is set, as for echo and
+/// printf's `%b`, `\0NNN` also takes three digits after its `0`.
+pub(crate) fn unescape_one(s: &[u8], zero_octal: bool, out: &mut Vec<u8>) -> Option<usize> {
+    let octal = |s: &[u8], max: usize| -> (u32, usize) {
+        let len = s
+            .iter()
+            .take(max)
+            .take_while(|b| (b'0'..=b'7').contains(*b))
+            .count();
+        let value = s[..len]
+            .iter()
+            .fold(0, |value, b| value * 8 + (b - b'0') as u32);
+        (value, len)
+    };
+    let hex = |s: &[u8], max: usize| -> (u32, usize) {
+        let len = s
+            .iter()
+            .take(max)
+            .take_while(|b| b.is_ascii_hexdigit())
+            .count();
+        let value = s[..len].iter().fold(0, |value, &b| {
+            value * 16 + (b as char).to_digit(16).unwrap()
+        });
+        (value, len)
+    };
+    let simple = match s.first() {
+        Some(b'a') => 0x07,
+        Some(b'b') => 0x08,
+        Some(b'e') => 0x1b,
+        Some(b'f') => 0x0c,
+        Some(b'n') => b'\n',
+        Some(b'r') => b'\r',
+        Some(b't') => b'\t',
+        Some(b'v') => 0x0b,
+        Some(b'\\') => b'\\',
+        Some(b'c') => return None,
+        Some(b'0') if zero_octal => {
+            let (value, len) = octal(&s[1..], 3);
+            out.push(value as u8);
+            return Some(1 + len);
+        }
+        Some(b'0'..=b'7') => {
+            // like GNU, values past \377 keep their low eight bits
+            let (value, len) = octal(s, 3);
+            out.push(value as u8);
+            return Some(len);
+        }
+        Some(b'x') => {
+            let (value, len) = hex(&s[1..], 2);
+            if len == 0 {
+                out.push(b'\\');
+                return Some(0);
+            }
+            out.push(value as u8);
+            return Some(1 + len);
+        }
+        Some(&kind @ b'u') | Some(&kind @ b'U') => {
+            let digits = if kind == b'u' { 4 } else { 8 };
+            let (value, len) = hex(&s[1..], digits);
+            match std::char::from_u32(value) {
+                Some(c) if len == digits => {
+                    let mut buf = [0; 4];
+                    out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                    return Some(1 + len);
+                }
+                _ => {
+                    out.push(b'\\');
+                    return Some(0);
+                }
+            }
+        }
+        _ => {
+            out.push(b'\\');
+            return Some(0);
+        }
+    };
+    out.push(simple);
+    Some(1)
+}
+
+/// Decode every backslash escape in `s` into `out`. Returns false if `\c`
+/// ended the output.
+pub(crate) fn unescape(s: &[u8], zero_octal: bool, out: &mut Vec<u8>) -> bool {
+    let mut i = 0;
+    while i < s.len() {
+        if s[i] != b'\\' || i + 1 == s.len() {
+            out.push(s[i]);
+            i += 1;
+            continue;
+        }
+        match unescape_one(&s[i + 1..], zero_octal, out) {
+            Some(used) => i += 1 + used,
+            None => return false,
+        }
+    }
+    true
+}
+
+impl Echo {
+    /// Parse command line arguments, returning the STRING operands.
+    /// As in GNU echo, options are the leading arguments made only of
+    /// `-` and the letters e, E and n, which may be clustered as in `-ne`;
+    /// `--help` and `--version` count only as the sole argument. Anything
+    /// else, `-` and `--` included, is printed.
+    pub fn parse<I, S>(&mut self, args: I) -> Vec<OsString>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let args: Vec<OsString> = args
+            .into_iter()
+            .map(|arg| arg.as_ref().to_os_string())
+            .collect();
+        let allow_options =
+            !self.option.posixly_correct || args.first().map(|arg| arg == "-n").unwrap_or(false);
+        if self.option.posixly_correct {
+            self.option.escapes = true;
+        }
+        if !allow_options {
+            return args;
+        }
+        if args.len() == 1 {
+            match args[0].to_str() {
+                // display this help and exit
+                Some("--help") => {
+                    self.option.display_help = true;
+                    return Vec::new();
+                }
+                // output version information and exit
+                Some("--version") => {
+                    self.option.display_version = true;
+                    return Vec::new();
+                }
+                _ => {}
+            }
+        }
+        let mut first = args.len();
+        for (i, v) in args.iter().enumerate() {
+            let letters = match v.to_str() {
+                Some(v) if v.len() > 1 && v.starts_with('-') => &v[1..],
+                _ => {
+                    first = i;
+                    break;
+                }
+            };
+            if !letters.bytes().all(|b| b == b'e' || b == b'E' || b == b'n') {
+                first = i;
+                break;
+            }
+            for letter in letters.bytes() {
+                match letter {
+                    // enable interpretation of backslash escapes
+                    b'e' => self.option.escapes = true,
+                    // disable interpretation of backslash escapes
+                    b'E' => self.option.escapes = self.option.posixly_correct,
+                    // do not output the trailing newline
+                    _ => self.option.no_newline = true,
+                }
+            }
+        }
+        args[first..].to_vec()
+    }
+
+    fn help<W: Write + ?Sized>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(
+            out,
+            "Usage: echo [SHORT-OPTION]... [STRING]...\n  \
+             or:  echo LONG-OPTION\n\
+             Echo the STRING(s) to standard output.\n\n  \
+             -n             do not output the trailing newline\n  \
+             -e             enable interpretation of backslash escapes\n  \
+             -E             disable interpretation of backslash escapes (default)\n      \
+             --help     display this help and exit\n      \
+             --version  output version information and exit"
+        )
+    }
+
+    fn version<W: Write + ?Sized>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "rust echo version 0.1.0")
+    }
+
+    /// Write `strings` separated by spaces to `out`.
+    pub fn run<S, W>(&self, strings: &[S], out: &mut W) -> io::Result<()>
+    where
+        S: AsRef<OsStr>,
+        W: Write,
+    {
+        let mut line: Vec<u8> = Vec::new();
+        for (i, s) in strings.iter().enumerate() {
+            if i > 0 {
+                line.push(b' ');
+            }
+            let s = cliopt::os_bytes(s.as_ref());
+            if !self.option.escapes {
+                line.extend_from_slice(&s);
+            } else if !unescape(&s, true, &mut line) {
+                // \c: no more output, not even the newline
+                return out.write_all(&line);
+            }
+        }
+        if !self.option.no_newline {
+            line.push(b'\n');
+        }
+        out.write_all(&line)
+    }
+}
+
+impl Utility for Echo {
+    fn name(&self) -> &str {
+        "echo"
+    }
+
+    fn parse_args(&mut self, args: &[OsString]) -> Result<(), String> {
+        self.args = args.to_vec();
+        Ok(())
+    }
+
+    fn execute(&mut self, ctx: &mut Context) -> i32 {
+        if ctx.var("POSIXLY_CORRECT").is_some() {
+            self.option.posixly_correct = true;
+        }
+        let args = std::mem::take(&mut self.args);
+        let strings = self.parse(&args);
+        let result = if self.option.display_help {
+            self.help(&mut ctx.stdout)
+        } else if self.option.display_version {
+            self.version(&mut ctx.stdout)
+        } else {
+            self.run(&strings, &mut ctx.stdout)
+        };
+        match result {
+            Ok(()) => 0,
+            Err(err) => {
+                writeln!(ctx.stderr, "echo: write error: {}", describe(&err)).unwrap();
+                1
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    #[test]
+    fn test_build() {
+        let e = EchoBuilder::new().build();
+        assert_eq!(EchoOption::default(), e.option);
+
+        let e = EchoBuilder::new().with_escapes().build();
+        assert_eq!(
+            EchoOption {
+                escapes: true,
+                ..Default::default()
+            },
+            e.option
+        );
+
+        let e = EchoBuilder::new().with_no_newline().build();
+        assert_eq!(
+            EchoOption {
+                no_newline: true,
+                ..Default::default()
+            },
+            e.option
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        {
+            let expects = EchoOption {
+                escapes: true,
+                no_newline: true,
+                ..Default::default()
+            };
+            let mut e = EchoBuilder::new().build();
+            let strings = e.parse(["-e", "-n", "a", "-E", "b"]);
+            assert_eq!(expects, e.option);
+            assert_eq!(strings, vec!["a", "-E", "b"]);
+        }
+
+        {
+            let mut e = EchoBuilder::new().with_escapes().build();
+            let strings = e.parse(["-E"]);
+            assert_eq!(EchoOption::default(), e.option);
+            assert!(strings.is_empty());
+        }
+
+        {
+            // clustered, up to the first argument that is not all e/E/n
+            let expects = EchoOption {
+                escapes: true,
+                no_newline: true,
+                ..Default::default()
+            };
+            let mut e = EchoBuilder::new().build();
+            let strings = e.parse(["-nEe", "-nq", "-n"]);
+            assert_eq!(expects, e.option);
+            assert_eq!(strings, vec!["-nq", "-n"]);
+        }
+
+        {
+            let mut e = EchoBuilder::new().build();
+            let strings = e.parse(["-", "-n"]);
+            assert_eq!(EchoOption::default(), e.option);
+            assert_eq!(strings, vec!["-", "-n"]);
+        }
+
+        {
+            // "--help"
+            let expects = EchoOption {
+                display_help: true,
+                ..Default::default()
+            };
+            let mut e = EchoBuilder::new().build();
+            let strings = e.parse(["--help"]);
+            assert_eq!(expects, e.option);
+            assert!(strings.is_empty());
+        }
+
+        {
+            // "--version" only when alone
+            let mut e = EchoBuilder::new().build();
+            let strings = e.parse(["--version", "x"]);
+            assert_eq!(EchoOption::default(), e.option);
+            assert_eq!(strings, vec!["--version", "x"]);
+        }
+
+        {
+            // POSIXLY_CORRECT: options only after a leading "-n"
+            let expects = EchoOption {
+                escapes: true,
+                posixly_correct: true,
+                ..Default::default()
+            };
+            let mut e = EchoBuilder::new().with_posixly_correct().build();
+            let strings = e.parse(["-E", "--help"]);
+            assert_eq!(expects, e.option);
+            assert_eq!(strings, vec!["-E", "--help"]);
+        }
+    }
+
+    #[test]
+    fn test_echo() {
+        let cases: &[&[&str]] = &[
+            &[],
+            &["hello", "world"],
+            &["-n", "no newline"],
+            &["-E", "a\\tb\\n"],
+            &["-e", "tab\\there", "line\\nbreak", "cr\\r"],
+            &["-e", "unknown \\q escape", "trailing\\"],
+            &["-e", "-n", "both"],
+            &["-n"],
+            &["not", "-n"],
+            &[
+                "-e", "bell\\a", "bs\\b", "esc\\e", "ff\\f", "vt\\v", "bs\\\\",
+            ],
+            &["-e", "\\0101", "\\01011", "\\08", "\\0", "\\0777"],
+            &["-e", "\\101", "\\1", "\\777", "\\8", "\\18"],
+            &["-e", "\\x41", "\\x4", "\\x414", "\\xg", "\\x", "\\xFf"],
+            &["-e", "a\\cb", "not printed"],
+            &["-e", "-n", "stop\\c"],
+            &["-e", "\\\\c", "escaped"],
+            &["-ne", "a\\tb"],
+            &["-neEq", "x"],
+            &["-", "-n"],
+            &["--", "-n"],
+            &["--help", "x"],
+            &["--version", "x"],
+        ];
+        for posixly_correct in [false, true].iter() {
+            for args in cases.iter() {
+                let mut command = process::Command::new("/bin/echo");
+                command.args(args.iter()).env_remove("POSIXLY_CORRECT");
+                let mut e = EchoBuilder::new().build();
+                let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+                let mut stdin: &[u8] = b"";
+                let mut out: Vec<u8> = Vec::new();
+                let mut err: Vec<u8> = Vec::new();
+                {
+                    let mut ctx = Context::new(&mut stdin, &mut out, &mut err);
+                    if *posixly_correct {
+                        ctx.env.push(("POSIXLY_CORRECT".into(), "1".into()));
+                        command.env("POSIXLY_CORRECT", "1");
+                    }
+                    assert_eq!(e.call(&args, &mut ctx), 0);
+                }
+                let expects = command.output().expect("Failed to execute command");
+                assert_eq!(
+                    out, expects.stdout,
+                    "echo {:?} (POSIXLY_CORRECT: {})",
+                    args, posixly_correct
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_call() {
+        // everything goes through the context, nothing to the process
+        let cases: &[(&[&str], &[u8])] = &[
+            (&["a", "b"], b"a b\n"),
+            (&["-n", "a"], b"a"),
+            (&["-e", "a\\tb\\c", "c"], b"a\tb"),
+            (&["--version", "x"], b"--version x\n"),
+        ];
+        for &(args, expects) in cases.iter() {
+            let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+            let mut stdin: &[u8] = b"";
+            let mut out: Vec<u8> = Vec::new();
+            let mut err: Vec<u8> = Vec::new();
+            let status = {
+                let mut ctx = Context::new(&mut stdin, &mut out, &mut err);
+                EchoBuilder::new().build().call(&args, &mut ctx)
+            };
+            assert_eq!((status, out, err), (0, expects.to_vec(), Vec::new()));
+        }
+
+        let mut stdin: &[u8] = b"";
+        let mut out: Vec<u8> = Vec::new();
+        let mut err: Vec<u8> = Vec::new();
+        let status = {
+            let mut ctx = Context::new(&mut stdin, &mut out, &mut err);
+            EchoBuilder::new()
+                .build()
+                .call(&[OsString::from("--help")], &mut ctx)
+        };
+        assert_eq!(status, 0);
+        assert!(out.starts_with(b"Usage: echo "));
+
+        // output that cannot be written is reported
+        struct Full;
+        impl Write for Full {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::Error::other("No space left on device"))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let mut err: Vec<u8> = Vec::new();
+        let status = {
+            let mut full = Full;
+            let mut ctx = Context::new(&mut stdin, &mut full, &mut err);
+            EchoBuilder::new()
+                .build()
+                .call(&[OsString::from("x")], &mut ctx)
+        };
+        assert_eq!(status, 1);
+        assert_eq!(
+            err,
+            b"echo: write error: No space left on device\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_unescape() {
+        let cases: &[(&[u8], &[u8], bool)] = &[
+            // GNU echo has no \u, so /bin/echo cannot check these
+            (b"\\u00e9\\U0001F600", "\u{e9}\u{1f600}".as_bytes(), true),
+            // too few digits or not a code point: kept as it is
+            (b"\\u12", b"\\u12", true),
+            (b"\\UD800DC00", b"\\UD800DC00", true),
+            (b"\\xff\\200", b"\xff\x80", true),
+            (b"a\\cb", b"a", false),
+        ];
+        for &(input, expects, more) in cases {
+            let mut out = Vec::new();
+            assert_eq!(unescape(input, true, &mut out), more, "{:?}", input);
+            assert_eq!(out, expects, "{:?}", input);
+        }
+
+        // printf formats take \0NNN as \0NN followed by N
+        let mut out = Vec::new();
+        unescape(b"\\0101", false, &mut out);
+        assert_eq!(out, b"\x081");
+    }
+}
@@ -0,0 +1,162 @@
+//! Waiting for a followed file to change, for `cat --follow`.
+use std::fs::Metadata;
+use std::path::Path;
+use std::time::Duration;
+
+/// How long to sleep between checks when change notification is not
+/// available, and the longest wait even when it is.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Wakes up when the directory holding the followed file changes: an
+/// append, a truncation, or a new file taking its name. Uses inotify on
+/// Linux and falls back to polling elsewhere or when inotify fails.
+pub struct Watcher {
+    #[cfg(target_os = "linux")]
+    inotify: Option<libc::c_int>,
+    interval: Duration,
+}
+
+impl Watcher {
+    pub fn new(path: &Path, interval: Duration) -> Watcher {
+        // watching the directory also sees the file being replaced
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        Watcher {
+            #[cfg(target_os = "linux")]
+            inotify: inotify::watch(dir),
+            interval,
+        }
+    }
+
+    /// Block until something may have changed, or for at most the interval.
+    pub fn wait(&mut self) {
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(fd) = self.inotify {
+                if inotify::wait(fd, self.interval) {
+                    return;
+                }
+                // the descriptor broke; poll from now on
+                inotify::close(fd);
+                self.inotify = None;
+            }
+        }
+        std::thread::sleep(self.interval);
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        #[cfg(target_os = "linux")]
+        {
+            if let Some(fd) = self.inotify.take() {
+                inotify::close(fd);
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::ffi::CString;
+    use std::path::Path;
+    use std::time::Duration;
+
+    pub fn watch(dir: &Path) -> Option<libc::c_int> {
+        use std::os::unix::ffi::OsStrExt;
+        let dir = CString::new(dir.as_os_str().as_bytes()).ok()?;
+        let mask = libc::IN_MODIFY
+            | libc::IN_ATTRIB
+            | libc::IN_CREATE
+            | libc::IN_MOVED_TO
+            | libc::IN_MOVED_FROM
+            | libc::IN_DELETE;
+        // SAFETY: plain system calls on a descriptor we own
+        unsafe {
+            let fd = libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC);
+            if fd < 0 {
+                return None;
+            }
+            if libc::inotify_add_watch(fd, dir.as_ptr(), mask) < 0 {
+                libc::close(fd);
+                return None;
+            }
+            Some(fd)
+        }
+    }
+
+    /// Wait for events on `fd` and discard them. Returns false if the
+    /// descriptor can no longer be used.
+    pub fn wait(fd: libc::c_int, timeout: Duration) -> bool {
+        let mut pollfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+        // SAFETY: pollfd and buf are valid for the duration of the calls
+        unsafe {
+            if libc::poll(&mut pollfd, 1, timeout) < 0 {
+                return std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted;
+            }
+            let mut buf = [0u8; 4096];
+            while libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) > 0 {}
+        }
+        true
+    }
+
+    pub fn close(fd: libc::c_int) {
+        // SAFETY: fd came from inotify_init1 and is closed once
+        unsafe {
+            libc::close(fd);
+        }
+    }
+}
+
+/// Whether two metadata describe the same file, so that a path that now
+/// names another file can be told from one that was only written to.
+#[cfg(unix)]
+pub fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+pub fn same_file(_a: &Metadata, _b: &Metadata) -> bool {
+    // without file identities only truncation can be noticed
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::Instant;
+
+    #[test]
+    fn test_wait_wakes_on_append() {
+        let dir = std::env::temp_dir().join(format!("rtw-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("log");
+        fs::write(&path, b"a\n").unwrap();
+        let first = fs::metadata(&path).unwrap();
+
+        let mut watcher = Watcher::new(&path, Duration::from_secs(5));
+        fs::write(&path, b"a\nb\n").unwrap();
+        let started = Instant::now();
+        watcher.wait();
+        if cfg!(target_os = "linux") {
+            assert!(started.elapsed() < Duration::from_secs(5));
+        }
+        assert!(same_file(&first, &fs::metadata(&path).unwrap()));
+
+        fs::rename(&path, dir.join("log.1")).unwrap();
+        fs::write(&path, b"c\n").unwrap();
+        if cfg!(unix) {
+            assert!(!same_file(&first, &fs::metadata(&path).unwrap()));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
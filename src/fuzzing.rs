@@ -0,0 +1,145 @@
+//! Entry points for the fuzz targets in fuzz/, also run over the
+//! regression corpus in fuzz/regressions by tests/fuzz_regressions.rs.
+//! Each takes the bytes the fuzzer made up and panics only on a bug.
+use super::cat::{CatBuilder, ColorWhen, Profile, Range};
+use super::echo::EchoBuilder;
+use super::printf::PrintfBuilder;
+use super::utility::{Context, Utility};
+use std::ffi::OsString;
+use std::io::{self, BufRead, Read};
+
+/// `data` split at NUL bytes into command line arguments.
+fn args(data: &[u8]) -> Vec<OsString> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+    data.split(|&b| b == 0).map(os_string).collect()
+}
+
+#[cfg(unix)]
+fn os_string(bytes: &[u8]) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+    OsString::from_vec(bytes.to_vec())
+}
+
+#[cfg(not(unix))]
+fn os_string(bytes: &[u8]) -> OsString {
+    OsString::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Parse `data` as the arguments of cat, under both profiles, and of echo
+/// and printf. The parsers must reject bad input with a message, never
+/// panic.
+pub fn parse_args(data: &[u8]) {
+    let args = args(data);
+    let _ = CatBuilder::new().build().parse(&args);
+    let _ = CatBuilder::new()
+        .with_profile(Profile::Bsd)
+        .build()
+        .parse(&args);
+    EchoBuilder::new().build().parse(&args);
+    let _ = PrintfBuilder::new().build().parse(&args);
+}
+
+/// Run printf with the arguments in `data`, in memory.
+pub fn printf(data: &[u8]) {
+    let args = args(data);
+    let mut stdin: &[u8] = b"";
+    let mut out: Vec<u8> = Vec::new();
+    let mut err: Vec<u8> = Vec::new();
+    let mut ctx = Context::new(&mut stdin, &mut out, &mut err);
+    PrintfBuilder::new().build().call(&args, &mut ctx);
+}
+
+/// A reader which hands out at most `chunk` bytes per read, to move the
+/// boundaries between reads around.
+struct Chunked<'a> {
+    data: &'a [u8],
+    chunk: usize,
+}
+
+impl<'a> Read for Chunked<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.fill_buf()?.len().min(buf.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<'a> BufRead for Chunked<'a> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let n = self.chunk.min(self.data.len());
+        Ok(&self.data[..n])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.data = &self.data[amt..];
+    }
+}
+
+/// Run cat over `data`. Its first two bytes choose the options and the
+/// third how many bytes each read gets; the rest is the input. The output
+/// must not depend on where the reads end. (`--binary` is left out: it
+/// judges by the first read alone.)
+pub fn cat_run(data: &[u8]) {
+    if data.len() < 3 {
+        return;
+    }
+    let flags = u16::from_le_bytes([data[0], data[1]]);
+    let chunk = data[2] as usize + 1;
+    let input = &data[3..];
+
+    let mut builder = CatBuilder::new();
+    let setters: [fn(&mut CatBuilder) -> &mut CatBuilder; 12] = [
+        CatBuilder::with_show_all,
+        CatBuilder::with_number_nonblank,
+        CatBuilder::with_e,
+        CatBuilder::with_show_ends,
+        CatBuilder::with_number,
+        CatBuilder::with_squeeze_blank,
+        CatBuilder::with_t,
+        CatBuilder::with_show_tabs,
+        CatBuilder::with_show_nonprinting,
+        CatBuilder::with_json,
+        CatBuilder::with_html,
+        CatBuilder::with_decompress,
+    ];
+    for (i, setter) in setters.iter().enumerate() {
+        if flags & (1 << i) != 0 {
+            setter(&mut builder);
+        }
+    }
+    if flags & (1 << 12) != 0 {
+        builder.with_profile(Profile::Bsd);
+    }
+    if flags & (1 << 13) != 0 {
+        let start = chunk as u64 % 7 + 1;
+        builder.with_bytes(Range::new(start, start + 9));
+    }
+    if flags & (1 << 14) != 0 {
+        builder.with_color(ColorWhen::Always);
+    }
+    if flags & (1 << 15) != 0 {
+        let start = chunk as u64 % 4 + 1;
+        builder.with_lines(Range::new(start, start + 2));
+    }
+
+    let run = |reader: &mut dyn BufRead| {
+        let mut out: Vec<u8> = Vec::new();
+        let mut err: Vec<u8> = Vec::new();
+        let mut cat = builder.build();
+        let result = cat.run(reader, &mut out, &mut err);
+        (result, out, err)
+    };
+    let whole = run(&mut &input[..]);
+    let chunked = run(&mut Chunked { data: input, chunk });
+    assert_eq!(
+        whole, chunked,
+        "flags {:#06x}, {} bytes per read",
+        flags, chunk
+    );
+    if flags == 0 {
+        assert_eq!(whole.1, input);
+    }
+}
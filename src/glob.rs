@@ -0,0 +1,183 @@
+#![allow(dead_code)]
+use super::cliopt::os_bytes;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+pub fn has_magic(pattern: &Path) -> bool {
+    os_bytes(pattern.as_os_str())
+        .iter()
+        .any(|&b| b == b'*' || b == b'?' || b == b'[')
+}
+
+/// Match one `[...]` class at the start of `pattern` against `c`.
+/// Returns the matching result and the length of the class, or `None` if
+/// the class is not terminated (then `[` is an ordinary character).
+fn match_class(pattern: &[u8], c: u8) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negate = i < pattern.len() && (pattern[i] == b'!' || pattern[i] == b'^');
+    if negate {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    while i < pattern.len() {
+        if pattern[i] == b']' && !first {
+            return Some((matched != negate, i + 1));
+        }
+        first = false;
+        let lo = pattern[i];
+        if i + 2 < pattern.len() && pattern[i + 1] == b'-' && pattern[i + 2] != b']' {
+            let hi = pattern[i + 2];
+            matched |= lo <= c && c <= hi;
+            i += 3;
+        } else {
+            matched |= lo == c;
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Shell-style wildcard match of a single file name: `*`, `?`, `[...]` and
+/// backslash escapes. A leading `.` must be matched explicitly.
+pub fn matches(pattern: &[u8], name: &[u8]) -> bool {
+    if name.first() == Some(&b'.') && pattern.first() != Some(&b'.') {
+        return false;
+    }
+    let (mut p, mut n) = (0, 0);
+    // position to retry from after the last `*`
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                b'*' => {
+                    star = Some((p, n));
+                    p += 1;
+                    continue;
+                }
+                b'?' => {
+                    p += 1;
+                    n += 1;
+                    continue;
+                }
+                b'[' => {
+                    if let Some((ok, len)) = match_class(&pattern[p..], name[n]) {
+                        if ok {
+                            p += len;
+                            n += 1;
+                            continue;
+                        }
+                    } else if name[n] == b'[' {
+                        p += 1;
+                        n += 1;
+                        continue;
+                    }
+                }
+                b'\\' if p + 1 < pattern.len() => {
+                    if pattern[p + 1] == name[n] {
+                        p += 2;
+                        n += 1;
+                        continue;
+                    }
+                }
+                c => {
+                    if c == name[n] {
+                        p += 1;
+                        n += 1;
+                        continue;
+                    }
+                }
+            }
+        }
+        match star {
+            Some((sp, sn)) => {
+                p = sp + 1;
+                n = sn + 1;
+                star = Some((sp, sn + 1));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|&b| b == b'*')
+}
+
+/// Expand a wildcard pattern into the sorted list of existing paths it
+/// matches. An empty result means nothing matched.
+pub fn expand(pattern: &Path) -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let part = match component {
+            Component::Normal(part) => part,
+            other => {
+                for path in &mut found {
+                    path.push(other.as_os_str());
+                }
+                continue;
+            }
+        };
+        if !has_magic(Path::new(part)) {
+            for path in &mut found {
+                path.push(part);
+            }
+            found.retain(|p| p.symlink_metadata().is_ok());
+            continue;
+        }
+        let mut next = Vec::new();
+        for dir in &found {
+            let read_from = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir.as_path()
+            };
+            let entries = match fs::read_dir(read_from) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            let mut names: Vec<_> = entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.file_name())
+                .filter(|name| matches(&os_bytes(part), &os_bytes(name)))
+                .collect();
+            names.sort();
+            next.extend(names.into_iter().map(|name| dir.join(name)));
+        }
+        found = next;
+    }
+    found.retain(|p| !p.as_os_str().is_empty());
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches(b"*.txt", b"cat_test.txt"));
+        assert!(!matches(b"*.txt", b"cat_test.txt.gz"));
+        assert!(matches(b"cat_????.txt", b"cat_test.txt"));
+        assert!(matches(b"[abc]at", b"cat"));
+        assert!(!matches(b"[!abc]at", b"cat"));
+        assert!(matches(b"[a-d]*", b"cat"));
+        assert!(matches(b"a*b*c", b"axxbyyc"));
+        assert!(!matches(b"a*b*c", b"axxbyy"));
+        assert!(matches(b"\\*", b"*"));
+        assert!(!matches(b"\\*", b"a"));
+        assert!(!matches(b"*", b".hidden"));
+        assert!(matches(b".*", b".hidden"));
+        assert!(matches(b"[", b"["));
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(
+            expand(Path::new("ci-tests/test-data/*.txt")),
+            vec![PathBuf::from("ci-tests/test-data/cat_test.txt")]
+        );
+        assert_eq!(
+            expand(Path::new("ci-tests/*-data/cat_test.txt")),
+            vec![PathBuf::from("ci-tests/test-data/cat_test.txt")]
+        );
+        assert!(expand(Path::new("ci-tests/*.none")).is_empty());
+    }
+}
@@ -0,0 +1,578 @@
+//! A streaming DEFLATE (RFC 1951) decoder and gzip (RFC 1952) reader.
+use std::io::{self, Read};
+
+const WINDOW_SIZE: usize = 32 * 1024;
+const MAX_BITS: usize = 15;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+}
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+/// Continue the CRC-32 `crc` over `data`.
+pub fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut c = !crc;
+    for &b in data {
+        c = CRC_TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8);
+    }
+    !c
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// LSB-first bit reader with its own input buffer.
+struct BitReader<R> {
+    inner: R,
+    buf: Box<[u8]>,
+    pos: usize,
+    len: usize,
+    bits: u64,
+    nbits: u32,
+}
+
+impl<R: Read> BitReader<R> {
+    fn new(inner: R) -> BitReader<R> {
+        BitReader {
+            inner,
+            buf: vec![0; 8192].into_boxed_slice(),
+            pos: 0,
+            len: 0,
+            bits: 0,
+            nbits: 0,
+        }
+    }
+
+    fn next_input_byte(&mut self) -> io::Result<Option<u8>> {
+        if self.pos == self.len {
+            self.pos = 0;
+            self.len = loop {
+                match self.inner.read(&mut self.buf) {
+                    Ok(n) => break n,
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                }
+            };
+            if self.len == 0 {
+                return Ok(None);
+            }
+        }
+        self.pos += 1;
+        Ok(Some(self.buf[self.pos - 1]))
+    }
+
+    /// Make at least `n` bits available, or as many as remain in the input.
+    fn fill(&mut self, n: u32) -> io::Result<()> {
+        while self.nbits < n {
+            match self.next_input_byte()? {
+                Some(b) => {
+                    self.bits |= (b as u64) << self.nbits;
+                    self.nbits += 8;
+                }
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn bits(&mut self, n: u32) -> io::Result<u32> {
+        self.fill(n)?;
+        if self.nbits < n {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "unexpected end of compressed data",
+            ));
+        }
+        let v = (self.bits & ((1u64 << n) - 1)) as u32;
+        self.bits >>= n;
+        self.nbits -= n;
+        Ok(v)
+    }
+
+    fn align(&mut self) {
+        let drop = self.nbits % 8;
+        self.bits >>= drop;
+        self.nbits -= drop;
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        self.align();
+        Ok(self.bits(8)? as u8)
+    }
+
+    fn u16_le(&mut self) -> io::Result<u16> {
+        Ok(self.byte()? as u16 | (self.byte()? as u16) << 8)
+    }
+
+    fn u32_le(&mut self) -> io::Result<u32> {
+        Ok(self.u16_le()? as u32 | (self.u16_le()? as u32) << 16)
+    }
+
+    fn at_end(&mut self) -> io::Result<bool> {
+        self.align();
+        self.fill(8)?;
+        Ok(self.nbits == 0)
+    }
+}
+
+/// Canonical Huffman code as a lookup table indexed by the next `bits`
+/// input bits. Each entry packs `symbol << 4 | code length`.
+struct Huffman {
+    table: Vec<u32>,
+    bits: u32,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> io::Result<Huffman> {
+        let mut count = [0u16; MAX_BITS + 1];
+        for &len in lengths {
+            count[len as usize] += 1;
+        }
+        count[0] = 0;
+        let mut left: i32 = 1;
+        for &c in count.iter().skip(1) {
+            left <<= 1;
+            left -= c as i32;
+            if left < 0 {
+                return Err(invalid("over-subscribed huffman code"));
+            }
+        }
+        let bits = (1..=MAX_BITS).rev().find(|&l| count[l] > 0).unwrap_or(1) as u32;
+        let mut next_code = [0u32; MAX_BITS + 2];
+        let mut code = 0u32;
+        for len in 1..=MAX_BITS {
+            code = (code + count[len - 1] as u32) << 1;
+            next_code[len] = code;
+        }
+        let mut table = vec![0u32; 1 << bits];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len == 0 {
+                continue;
+            }
+            let len = len as u32;
+            let code = next_code[len as usize];
+            next_code[len as usize] += 1;
+            // codes are stored most significant bit first in the stream
+            let mut reversed = 0u32;
+            for i in 0..len {
+                reversed |= ((code >> i) & 1) << (len - 1 - i);
+            }
+            let mut index = reversed as usize;
+            while index < table.len() {
+                table[index] = (symbol as u32) << 4 | len;
+                index += 1 << len;
+            }
+        }
+        Ok(Huffman { table, bits })
+    }
+
+    fn decode<R: Read>(&self, input: &mut BitReader<R>) -> io::Result<u16> {
+        input.fill(self.bits)?;
+        let mask = (1u64 << self.bits) - 1;
+        let entry = self.table[(input.bits & mask) as usize];
+        let len = entry & 0xf;
+        if len == 0 {
+            return Err(invalid("invalid huffman code"));
+        }
+        if len > input.nbits {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "unexpected end of compressed data",
+            ));
+        }
+        input.bits >>= len;
+        input.nbits -= len;
+        Ok((entry >> 4) as u16)
+    }
+}
+
+enum Block {
+    /// the next thing in the stream is a block header
+    Header,
+    Stored(usize),
+    Codes(Huffman, Huffman),
+}
+
+enum Member {
+    Header,
+    Body,
+    Done,
+}
+
+/// Reads the decompressed contents of a gzip stream, including streams made
+/// of several concatenated members.
+pub struct GzDecoder<R> {
+    input: BitReader<R>,
+    member: Member,
+    block: Block,
+    last_block: bool,
+    members: u32,
+    crc: u32,
+    size: u32,
+    /// decoded output, holding at least the last window for back references
+    history: Vec<u8>,
+    /// how much of `history` has been handed out by `read`
+    served: usize,
+}
+
+impl<R: Read> GzDecoder<R> {
+    pub fn new(inner: R) -> GzDecoder<R> {
+        GzDecoder {
+            input: BitReader::new(inner),
+            member: Member::Header,
+            block: Block::Header,
+            last_block: false,
+            members: 0,
+            crc: 0,
+            size: 0,
+            history: Vec::with_capacity(3 * WINDOW_SIZE),
+            served: 0,
+        }
+    }
+
+    /// Read a member header. Returns false if the data after the first
+    /// member is not another member, which gzip ignores as trailing garbage.
+    fn read_header(&mut self) -> io::Result<bool> {
+        let input = &mut self.input;
+        if input.byte()? != 0x1f || input.byte()? != 0x8b {
+            if self.members > 0 {
+                return Ok(false);
+            }
+            return Err(invalid("not in gzip format"));
+        }
+        if input.byte()? != 8 {
+            return Err(invalid("unknown compression method"));
+        }
+        let flags = input.byte()?;
+        // MTIME, XFL, OS
+        for _ in 0..6 {
+            input.byte()?;
+        }
+        if flags & 0x04 != 0 {
+            let xlen = input.u16_le()?;
+            for _ in 0..xlen {
+                input.byte()?;
+            }
+        }
+        // FNAME, FCOMMENT
+        for flag in &[0x08, 0x10] {
+            if flags & flag != 0 {
+                while input.byte()? != 0 {}
+            }
+        }
+        if flags & 0x02 != 0 {
+            input.u16_le()?;
+        }
+        self.crc = 0;
+        self.size = 0;
+        self.last_block = false;
+        self.block = Block::Header;
+        self.members += 1;
+        Ok(true)
+    }
+
+    fn read_trailer(&mut self) -> io::Result<()> {
+        let crc = self.input.u32_le()?;
+        let size = self.input.u32_le()?;
+        if crc != self.crc {
+            return Err(invalid("invalid compressed data--crc error"));
+        }
+        if size != self.size {
+            return Err(invalid("invalid compressed data--length error"));
+        }
+        Ok(())
+    }
+
+    fn read_block_header(&mut self) -> io::Result<()> {
+        self.last_block = self.input.bits(1)? == 1;
+        self.block = match self.input.bits(2)? {
+            0 => {
+                let len = self.input.u16_le()?;
+                let nlen = self.input.u16_le()?;
+                if len != !nlen {
+                    return Err(invalid("invalid stored block lengths"));
+                }
+                Block::Stored(len as usize)
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                for (symbol, len) in lengths.iter_mut().enumerate() {
+                    *len = match symbol {
+                        0..=143 => 8,
+                        144..=255 => 9,
+                        256..=279 => 7,
+                        _ => 8,
+                    };
+                }
+                Block::Codes(Huffman::new(&lengths)?, Huffman::new(&[5; 30])?)
+            }
+            2 => self.read_dynamic_codes()?,
+            _ => return Err(invalid("invalid block type")),
+        };
+        Ok(())
+    }
+
+    fn read_dynamic_codes(&mut self) -> io::Result<Block> {
+        let hlit = self.input.bits(5)? as usize + 257;
+        let hdist = self.input.bits(5)? as usize + 1;
+        let hclen = self.input.bits(4)? as usize + 4;
+        if hlit > 286 || hdist > 30 {
+            return Err(invalid("too many length or distance codes"));
+        }
+        let mut cl_lengths = [0u8; 19];
+        for &i in CODE_LENGTH_ORDER.iter().take(hclen) {
+            cl_lengths[i] = self.input.bits(3)? as u8;
+        }
+        let cl = Huffman::new(&cl_lengths)?;
+        let mut lengths = vec![0u8; hlit + hdist];
+        let mut i = 0;
+        while i < lengths.len() {
+            let symbol = cl.decode(&mut self.input)?;
+            let (value, repeat) = match symbol {
+                0..=15 => (symbol as u8, 1),
+                16 => {
+                    if i == 0 {
+                        return Err(invalid("repeat with no first length"));
+                    }
+                    (lengths[i - 1], 3 + self.input.bits(2)? as usize)
+                }
+                17 => (0, 3 + self.input.bits(3)? as usize),
+                _ => (0, 11 + self.input.bits(7)? as usize),
+            };
+            if i + repeat > lengths.len() {
+                return Err(invalid("too many code lengths"));
+            }
+            for len in &mut lengths[i..i + repeat] {
+                *len = value;
+            }
+            i += repeat;
+        }
+        if lengths[256] == 0 {
+            return Err(invalid("missing end-of-block code"));
+        }
+        Ok(Block::Codes(
+            Huffman::new(&lengths[..hlit])?,
+            Huffman::new(&lengths[hlit..])?,
+        ))
+    }
+
+    /// Decode until about a window of output is ready or the member ends.
+    fn decode_some(&mut self) -> io::Result<()> {
+        let start = self.history.len();
+        while self.history.len() - start < WINDOW_SIZE {
+            match self.member {
+                Member::Done => break,
+                Member::Header => {
+                    if self.members > 0 && self.input.at_end()? {
+                        self.member = Member::Done;
+                        continue;
+                    }
+                    self.member = if self.read_header()? {
+                        Member::Body
+                    } else {
+                        Member::Done
+                    };
+                }
+                Member::Body => {
+                    if let Block::Header = self.block {
+                        if self.last_block {
+                            break;
+                        }
+                        self.read_block_header()?;
+                    }
+                    self.inflate_block(start + WINDOW_SIZE)?;
+                }
+            }
+        }
+        let produced = &self.history[start..];
+        self.crc = crc32(self.crc, produced);
+        self.size = self.size.wrapping_add(produced.len() as u32);
+        if let (&Member::Body, &Block::Header, true) = (&self.member, &self.block, self.last_block)
+        {
+            self.read_trailer()?;
+            self.member = Member::Header;
+        }
+        Ok(())
+    }
+
+    /// Decode from the current block until it ends or `limit` bytes of
+    /// history exist.
+    fn inflate_block(&mut self, limit: usize) -> io::Result<()> {
+        match self.block {
+            Block::Header => Ok(()),
+            Block::Stored(ref mut remaining) => {
+                while *remaining > 0 && self.history.len() < limit {
+                    self.history.push(self.input.byte()?);
+                    *remaining -= 1;
+                }
+                if *remaining == 0 {
+                    self.block = Block::Header;
+                }
+                Ok(())
+            }
+            Block::Codes(ref lit, ref dist) => {
+                while self.history.len() < limit {
+                    let symbol = lit.decode(&mut self.input)? as usize;
+                    if symbol < 256 {
+                        self.history.push(symbol as u8);
+                        continue;
+                    }
+                    if symbol == 256 {
+                        self.block = Block::Header;
+                        return Ok(());
+                    }
+                    let symbol = symbol - 257;
+                    if symbol >= LENGTH_BASE.len() {
+                        return Err(invalid("invalid literal/length code"));
+                    }
+                    let len = LENGTH_BASE[symbol] as usize
+                        + self.input.bits(LENGTH_EXTRA[symbol] as u32)? as usize;
+                    let symbol = dist.decode(&mut self.input)? as usize;
+                    if symbol >= DIST_BASE.len() {
+                        return Err(invalid("invalid distance code"));
+                    }
+                    let distance = DIST_BASE[symbol] as usize
+                        + self.input.bits(DIST_EXTRA[symbol] as u32)? as usize;
+                    if distance > self.history.len() {
+                        return Err(invalid("invalid distance too far back"));
+                    }
+                    let from = self.history.len() - distance;
+                    for i in 0..len {
+                        let b = self.history[from + i];
+                        self.history.push(b);
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<R: Read> Read for GzDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.served == self.history.len() {
+            if let Member::Done = self.member {
+                return Ok(0);
+            }
+            if self.served > 2 * WINDOW_SIZE {
+                // keep only the window needed for back references
+                self.history.drain(..self.served - WINDOW_SIZE);
+                self.served = WINDOW_SIZE;
+            }
+            self.decode_some()?;
+        }
+        let n = buf.len().min(self.history.len() - self.served);
+        buf[..n].copy_from_slice(&self.history[self.served..self.served + n]);
+        self.served += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    fn gunzip(data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        GzDecoder::new(data).read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(0, b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn test_stored_and_fixed() {
+        // `printf 'hello\n' | gzip -n` and a hand made stored block
+        let fixed: &[u8] = &[
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xcb, 0x48, 0xcd, 0xc9,
+            0xc9, 0xe7, 0x02, 0x00, 0x20, 0x30, 0x3a, 0x36, 0x06, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(gunzip(fixed).unwrap(), b"hello\n".to_vec());
+
+        let mut stored = vec![0x1f, 0x8b, 0x08, 0x00, 0, 0, 0, 0, 0x00, 0x03];
+        stored.extend_from_slice(&[0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c']);
+        stored.extend_from_slice(&crc32(0, b"abc").to_le_bytes());
+        stored.extend_from_slice(&3u32.to_le_bytes());
+        assert_eq!(gunzip(&stored).unwrap(), b"abc".to_vec());
+
+        // concatenated members decode as one stream, trailing garbage is ignored
+        let mut both = fixed.to_vec();
+        both.extend_from_slice(&stored);
+        both.extend_from_slice(&[0, 0, 0, 0]);
+        assert_eq!(gunzip(&both).unwrap(), b"hello\nabc".to_vec());
+    }
+
+    #[test]
+    fn test_corrupt() {
+        assert!(gunzip(b"plain text").is_err());
+        let truncated: &[u8] = &[0x1f, 0x8b, 0x08, 0x00, 0, 0, 0, 0, 0x00, 0x03, 0xcb, 0x48];
+        assert!(gunzip(truncated).is_err());
+    }
+
+    #[test]
+    fn test_against_gzip() {
+        // dynamic huffman blocks with long back references
+        let mut data = Vec::new();
+        for i in 0..20000u32 {
+            data.extend_from_slice(format!("line {} {}\n", i, i * 7919 % 1000).as_bytes());
+        }
+        let mut child = match process::Command::new("gzip")
+            .arg("-c")
+            .stdin(process::Stdio::piped())
+            .stdout(process::Stdio::piped())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(_) => return, // gzip is not installed
+        };
+        {
+            use std::io::Write;
+            let mut stdin = child.stdin.take().unwrap();
+            let data = data.clone();
+            std::thread::spawn(move || stdin.write_all(&data).unwrap());
+        }
+        let compressed = child.wait_with_output().unwrap().stdout;
+        assert_eq!(gunzip(&compressed).unwrap(), data);
+    }
+}
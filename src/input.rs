@@ -0,0 +1,152 @@
+//! Opening FILE operands, including those `File::open` cannot read.
+use super::vfs::MemoryFile;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+/// An opened FILE operand: a regular file, FIFO or device, a
+/// connection to a UNIX domain socket, or a file of a `MemoryFs`.
+pub enum Input {
+    File(File),
+    #[cfg(unix)]
+    Socket(UnixStream),
+    Memory(MemoryFile),
+}
+
+impl Input {
+    /// Open the FILE operand `path`. A socket is connected to as a stream
+    /// client which only reads, like BSD cat does.
+    pub fn open(path: &Path) -> io::Result<Input> {
+        #[cfg(unix)]
+        {
+            use std::net::Shutdown;
+            use std::os::unix::fs::FileTypeExt;
+            if fs::metadata(path)?.file_type().is_socket() {
+                let stream = UnixStream::connect(path)?;
+                // tell the server nothing is coming; some wait for that
+                stream.shutdown(Shutdown::Write)?;
+                return Ok(Input::Socket(stream));
+            }
+        }
+        File::open(path).map(Input::File)
+    }
+
+    /// Whether opening or reading `path` may wait on another process, as
+    /// for a FIFO without a writer or a socket. Output written so far
+    /// should be flushed first, so that it is not held back meanwhile.
+    pub fn may_block(path: &Path) -> bool {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            if let Ok(meta) = fs::metadata(path) {
+                let file_type = meta.file_type();
+                return file_type.is_fifo() || file_type.is_socket();
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = path;
+        false
+    }
+
+    /// The underlying file, for seeking; sockets and files in memory
+    /// have none.
+    pub fn file_mut(&mut self) -> Option<&mut File> {
+        match self {
+            Input::File(file) => Some(file),
+            #[cfg(unix)]
+            Input::Socket(_) => None,
+            Input::Memory(_) => None,
+        }
+    }
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::File(file) => file.read(buf),
+            #[cfg(unix)]
+            Input::Socket(stream) => stream.read(buf),
+            Input::Memory(file) => file.read(buf),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use std::io::Write;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::UnixListener;
+    use std::path::PathBuf;
+    use std::thread;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rtw-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_socket() {
+        let dir = temp_dir("socket");
+        let path = dir.join("app.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // the client shuts down its side without sending anything
+            let mut request = Vec::new();
+            stream.read_to_end(&mut request).unwrap();
+            assert!(request.is_empty());
+            stream.write_all(b"hello\nfrom socket\n").unwrap();
+        });
+
+        assert!(Input::may_block(&path));
+        let mut input = Input::open(&path).unwrap();
+        assert!(input.file_mut().is_none());
+        let mut out = String::new();
+        input.read_to_string(&mut out).unwrap();
+        assert_eq!(out, "hello\nfrom socket\n");
+        server.join().unwrap();
+
+        // nobody listens any more
+        drop(input);
+        fs::remove_file(&path).unwrap();
+        UnixListener::bind(&path).map(drop).unwrap();
+        let err = Input::open(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fifo() {
+        let dir = temp_dir("fifo");
+        let path = dir.join("pipe");
+        let cpath = CString::new(path.as_os_str().as_bytes()).unwrap();
+        // SAFETY: cpath is a valid NUL-terminated string
+        assert_eq!(unsafe { libc::mkfifo(cpath.as_ptr(), 0o600) }, 0);
+        assert!(Input::may_block(&path));
+
+        let writer_path = path.clone();
+        let writer = thread::spawn(move || {
+            let mut f = fs::OpenOptions::new()
+                .write(true)
+                .open(writer_path)
+                .unwrap();
+            f.write_all(b"through a fifo\n").unwrap();
+        });
+        let mut input = Input::open(&path).unwrap();
+        let mut out = String::new();
+        input.read_to_string(&mut out).unwrap();
+        assert_eq!(out, "through a fifo\n");
+        writer.join().unwrap();
+
+        assert!(!Input::may_block(Path::new(
+            "ci-tests/test-data/cat_test.txt"
+        )));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,70 @@
+//! Minimal JSON writing helpers.
+
+/// Append `bytes` to `out` as a JSON string literal. Bytes that are not part
+/// of valid UTF-8 are replaced with U+FFFD, as `String::from_utf8_lossy`
+/// does, so the output is always valid JSON; callers that need the exact
+/// bytes write them separately with `push_hex`.
+pub fn push_string(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.push(b'"');
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '"' => out.extend_from_slice(b"\\\""),
+                '\\' => out.extend_from_slice(b"\\\\"),
+                '\n' => out.extend_from_slice(b"\\n"),
+                '\r' => out.extend_from_slice(b"\\r"),
+                '\t' => out.extend_from_slice(b"\\t"),
+                '\u{8}' => out.extend_from_slice(b"\\b"),
+                '\u{c}' => out.extend_from_slice(b"\\f"),
+                c if (c as u32) < 0x20 => {
+                    out.extend_from_slice(format!("\\u{:04x}", c as u32).as_bytes())
+                }
+                c => {
+                    let mut buf = [0; 4];
+                    out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+        }
+        if !chunk.invalid().is_empty() {
+            out.extend_from_slice("\u{fffd}".as_bytes());
+        }
+    }
+    out.push(b'"');
+}
+
+/// Append `bytes` to `out` as a JSON string of lowercase hex digits, two per
+/// byte.
+pub fn push_hex(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.push(b'"');
+    for &b in bytes {
+        out.extend_from_slice(format!("{:02x}", b).as_bytes());
+    }
+    out.push(b'"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(bytes: &[u8]) -> String {
+        let mut out = Vec::new();
+        push_string(&mut out, bytes);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_push_string() {
+        assert_eq!(string(b"plain"), r#""plain""#);
+        assert_eq!(string(b"q\"b\\"), r#""q\"b\\""#);
+        assert_eq!(string(b"\t\r\n\x01\x7f"), "\"\\t\\r\\n\\u0001\x7f\"");
+        assert_eq!(string("héllo".as_bytes()), "\"héllo\"");
+        assert_eq!(string(b"a\xffb\xc3"), "\"a\u{fffd}b\u{fffd}\"");
+    }
+
+    #[test]
+    fn test_push_hex() {
+        let mut out = Vec::new();
+        push_hex(&mut out, b"\xfe\tz");
+        assert_eq!(out, b"\"fe097a\"".to_vec());
+    }
+}
@@ -1,2 +1,29 @@
+#[cfg(feature = "bzip2")]
+extern crate bzip2_rs;
+#[cfg(unix)]
+extern crate libc;
+#[cfg(feature = "xz")]
+extern crate lzma_rs;
+#[cfg(feature = "zstd")]
+extern crate ruzstd;
+
 pub mod cat;
 mod cliopt;
+mod color;
+mod decompress;
+pub mod echo;
+mod follow;
+#[doc(hidden)]
+pub mod fuzzing;
+mod glob;
+mod inflate;
+mod input;
+mod json;
+pub mod multicall;
+pub mod printf;
+mod state;
+mod timestamp;
+mod utility;
+pub mod vfs;
+
+pub use utility::{run_std, Context, Utility};
@@ -0,0 +1,267 @@
+//! Every tool in one executable, chosen by the name it is run as or by
+//! its first argument, like busybox.
+use super::cat::{CatBuilder, Profile};
+use super::echo::EchoBuilder;
+use super::printf::PrintfBuilder;
+use super::utility::{Context, Utility};
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub struct Applet {
+    pub name: &'static str,
+    pub new: fn() -> Box<dyn Utility>,
+}
+
+/// The applets, sorted by name.
+pub const APPLETS: &[Applet] = &[
+    Applet {
+        name: "bsdcat",
+        new: || Box::new(CatBuilder::new().with_profile(Profile::Bsd).build()),
+    },
+    Applet {
+        name: "cat",
+        new: || Box::new(CatBuilder::new().build()),
+    },
+    Applet {
+        name: "echo",
+        new: || Box::new(EchoBuilder::new().build()),
+    },
+    Applet {
+        name: "printf",
+        new: || Box::new(PrintfBuilder::new().build()),
+    },
+];
+
+/// The applet called `name`, which may be a path such as argv[0].
+pub fn find(name: &Path) -> Option<&'static Applet> {
+    let name = name.file_name()?;
+    APPLETS.iter().find(|applet| name == applet.name)
+}
+
+/// Link each applet's name in `dir` to `exe`. Returns the links made;
+/// names already taken in `dir` are left alone and reported as errors.
+pub fn install(dir: &Path, exe: &Path) -> Vec<io::Result<PathBuf>> {
+    APPLETS
+        .iter()
+        .map(|applet| {
+            let link = dir.join(applet.name);
+            symlink(exe, &link).map(|_| link.clone()).map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!("{}: {}", link.display(), super::cat::describe(&err)),
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(not(unix))]
+fn symlink(_target: &Path, _link: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "symbolic links are not supported here",
+    ))
+}
+
+/// `rtw` run under its own name: the first argument names the applet.
+pub struct Multicall {
+    /// where `--install` links point to
+    exe: PathBuf,
+    args: Vec<OsString>,
+    /// the applet run, once there is one
+    applet: Option<&'static Applet>,
+}
+
+impl Multicall {
+    pub fn new(exe: &Path) -> Multicall {
+        Multicall {
+            exe: exe.to_path_buf(),
+            args: Vec::new(),
+            applet: None,
+        }
+    }
+
+    fn usage<W: Write + ?Sized>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(
+            out,
+            "Usage: rtw APPLET [ARG]...\n   \
+             or: APPLET [ARG]...  (run through a link named APPLET)\n   \
+             or: rtw --list\n   \
+             or: rtw --install DIR\n\n\
+             Currently defined applets:"
+        )?;
+        for applet in APPLETS {
+            writeln!(out, "  {}", applet.name)?;
+        }
+        Ok(())
+    }
+}
+
+impl Utility for Multicall {
+    fn name(&self) -> &str {
+        self.applet.map_or("rtw", |applet| applet.name)
+    }
+
+    fn parse_args(&mut self, args: &[OsString]) -> Result<(), String> {
+        self.args = args.to_vec();
+        Ok(())
+    }
+
+    fn execute(&mut self, ctx: &mut Context) -> i32 {
+        let first = match self.args.first() {
+            Some(first) => first.clone(),
+            None => {
+                let _ = self.usage(&mut ctx.stderr);
+                return 1;
+            }
+        };
+        match first.to_str() {
+            Some("--help") => match self.usage(&mut ctx.stdout) {
+                Ok(()) => 0,
+                Err(_) => 1,
+            },
+            Some("--list") => {
+                for applet in APPLETS {
+                    if writeln!(ctx.stdout, "{}", applet.name).is_err() {
+                        return 1;
+                    }
+                }
+                0
+            }
+            Some("--install") => {
+                let dir = match self.args.get(1) {
+                    Some(dir) if self.args.len() == 2 => PathBuf::from(dir),
+                    _ => {
+                        writeln!(ctx.stderr, "rtw: --install takes one directory").unwrap();
+                        return 1;
+                    }
+                };
+                let mut status = 0;
+                for result in install(&dir, &self.exe) {
+                    if let Err(err) = result {
+                        writeln!(ctx.stderr, "rtw: {}", err).unwrap();
+                        status = 1;
+                    }
+                }
+                status
+            }
+            _ => match find(Path::new(&first)) {
+                Some(applet) => {
+                    self.applet = Some(applet);
+                    (applet.new)().call(&self.args[1..], ctx)
+                }
+                None => {
+                    writeln!(
+                        ctx.stderr,
+                        "rtw: applet not found: {}",
+                        first.to_string_lossy()
+                    )
+                    .unwrap();
+                    127
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn multicall(args: &[&str], stdin: &[u8]) -> (i32, String, String) {
+        let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+        let mut stdin = stdin;
+        let mut stdout: Vec<u8> = Vec::new();
+        let mut stderr: Vec<u8> = Vec::new();
+        let status = {
+            let mut ctx = Context::new(&mut stdin, &mut stdout, &mut stderr);
+            Multicall::new(Path::new("/bin/rtw")).call(&args, &mut ctx)
+        };
+        (
+            status,
+            String::from_utf8(stdout).unwrap(),
+            String::from_utf8(stderr).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_dispatch() {
+        assert_eq!(
+            multicall(&["cat", "-n"], b"x\n"),
+            (0, "     1\tx\n".to_string(), String::new())
+        );
+        assert_eq!(
+            multicall(&["/usr/bin/bsdcat", "-n", "-"], b"x\n"),
+            (0, "     1\tx\n".to_string(), String::new())
+        );
+        assert_eq!(
+            multicall(&["echo", "-ne", "a\\tb"], b""),
+            (0, "a\tb".to_string(), String::new())
+        );
+        assert_eq!(
+            multicall(&["dog"], b""),
+            (
+                127,
+                String::new(),
+                "rtw: applet not found: dog\n".to_string()
+            )
+        );
+        assert_eq!(
+            multicall(&["--list"], b""),
+            (0, "bsdcat\ncat\necho\nprintf\n".to_string(), String::new())
+        );
+        let (status, _, usage) = multicall(&[], b"");
+        assert_eq!(status, 1);
+        assert!(usage.starts_with("Usage: rtw APPLET"));
+
+        // errors reported after the run are in the applet's name
+        let mut m = Multicall::new(Path::new("/bin/rtw"));
+        assert_eq!(m.name(), "rtw");
+        let mut stdin = &b""[..];
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        m.call(
+            &["echo".into()],
+            &mut Context::new(&mut stdin, &mut stdout, &mut stderr),
+        );
+        assert_eq!(m.name(), "echo");
+
+        assert!(APPLETS.windows(2).all(|w| w[0].name < w[1].name));
+        assert!(find(Path::new("./cat")).is_some());
+        assert!(find(Path::new("rtw")).is_none());
+
+        // every standalone binary is also an applet
+        let bins =
+            std::fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("src/bin")).unwrap();
+        for entry in bins {
+            let path = entry.unwrap().path();
+            let name = path.file_stem().unwrap();
+            if name != "rtw" {
+                assert!(find(Path::new(name)).is_some(), "no applet for {:?}", name);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_install() {
+        let dir = std::env::temp_dir().join(format!("rtw-install-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("cat"), b"taken").unwrap();
+
+        let (status, _, err) = multicall(&["--install", dir.to_str().unwrap()], b"");
+        assert_eq!(status, 1);
+        assert_eq!(err, format!("rtw: {}/cat: File exists\n", dir.display()));
+        assert_eq!(
+            std::fs::read_link(dir.join("bsdcat")).unwrap(),
+            PathBuf::from("/bin/rtw")
+        );
+        assert_eq!(std::fs::read(dir.join("cat")).unwrap(), b"taken".to_vec());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,1068 @@
+#![allow(dead_code)]
+//! `printf`: format and print data, following GNU coreutils.
+//!
+//! Arguments are taken as bytes, as in the C locale: `%c` prints one byte,
+//! `'c` gives the value of one byte and `%q` escapes non-ASCII bytes.
+//! Floating point conversions are done in double precision.
+use super::cat::describe;
+use super::cliopt;
+use super::echo;
+use super::utility::{Context, Utility};
+use std::ffi::OsString;
+use std::io::{self, Write};
+
+#[derive(Clone, Default, PartialEq, Debug)]
+struct PrintfOption {
+    display_help: bool,
+    display_version: bool,
+}
+
+pub struct PrintfBuilder {
+    option: PrintfOption,
+}
+
+pub struct Printf {
+    option: PrintfOption,
+    format: Vec<u8>,
+    args: Vec<Vec<u8>>,
+}
+
+/// The flags, width and precision of one conversion specification.
+#[derive(Clone, Default, PartialEq, Debug)]
+struct Spec {
+    /// `-`
+    left: bool,
+    /// `+`
+    plus: bool,
+    /// ` `
+    space: bool,
+    /// `#`
+    alt: bool,
+    /// `0`
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+/// What formatting one pass over FORMAT produced.
+struct Output {
+    out: Vec<u8>,
+    /// diagnostics, each ready to print after "printf: "
+    errors: Vec<String>,
+    /// whether a conversion error sets the exit status
+    failed: bool,
+}
+
+/// How a pass over FORMAT ended.
+enum Pass {
+    /// through the end of FORMAT, having used this many arguments
+    Done(usize),
+    /// at `\c`: nothing more is output
+    Stop,
+}
+
+impl Default for PrintfBuilder {
+    fn default() -> PrintfBuilder {
+        PrintfBuilder::new()
+    }
+}
+
+impl PrintfBuilder {
+    pub fn new() -> PrintfBuilder {
+        PrintfBuilder {
+            option: PrintfOption::default(),
+        }
+    }
+    pub fn build(&self) -> Printf {
+        Printf {
+            option: self.option.clone(),
+            format: Vec::new(),
+            args: Vec::new(),
+        }
+    }
+}
+
+/// `arg` quoted for a diagnostic, as in `'abc': expected a numeric value`,
+/// with C escapes for quotes, backslashes and unprintable bytes.
+fn quote(arg: &[u8]) -> String {
+    let mut quoted = String::from("'");
+    for &b in arg {
+        match b {
+            b'\'' | b'\\' => {
+                quoted.push('\\');
+                quoted.push(b as char);
+            }
+            b'\n' => quoted.push_str("\\n"),
+            b'\t' => quoted.push_str("\\t"),
+            0x20..=0x7e => quoted.push(b as char),
+            _ => quoted.push_str(&format!("\\{:03o}", b)),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// Decode the backslash escape at the start of `s`, the backslash already
+/// taken, as printf does in FORMAT and in `%b` arguments. Like
+/// `echo::unescape_one`, but `\"` is a quote and a `\x`, `\u` or `\U`
+/// without its digits is an error.
+fn unescape_one(s: &[u8], zero_octal: bool, out: &mut Vec<u8>) -> Result<Option<usize>, String> {
+    let digits = match s.first() {
+        Some(b'"') => {
+            out.push(b'"');
+            return Ok(Some(1));
+        }
+        Some(b'x') => 1,
+        Some(b'u') => 4,
+        Some(b'U') => 8,
+        _ => return Ok(echo::unescape_one(s, zero_octal, out)),
+    };
+    let hex = &s[1..];
+    let len = hex
+        .iter()
+        .take(digits)
+        .take_while(|b| b.is_ascii_hexdigit())
+        .count();
+    if len < digits {
+        return Err("missing hexadecimal number in escape".to_string());
+    }
+    if s[0] != b'x' {
+        let code = u32::from_str_radix(std::str::from_utf8(&hex[..len]).unwrap(), 16).unwrap();
+        // C99 universal character names exclude these
+        if (code < 0xa0 && code != 0x24 && code != 0x40 && code != 0x60)
+            || (0xd800..=0xdfff).contains(&code)
+            || std::char::from_u32(code).is_none()
+        {
+            return Err(if s[0] == b'u' {
+                format!("invalid universal character name \\u{:04x}", code)
+            } else {
+                format!("invalid universal character name \\U{:08x}", code)
+            });
+        }
+    }
+    Ok(echo::unescape_one(s, zero_octal, out))
+}
+
+/// `arg` as an integer, for `%d` and friends: decimal, octal with a
+/// leading `0`, hexadecimal with a leading `0x`, or the value of the byte
+/// after a leading `'` or `"`. Returns the value, clamped to the range of
+/// `i64` or, for `unsigned`, wrapped into `u64`, and any error about it.
+fn parse_int(arg: &[u8], unsigned: bool, errors: &mut Vec<String>) -> (i128, bool) {
+    if let Some(value) = char_constant(arg, errors) {
+        return (value as i128, true);
+    }
+    let start = arg.iter().take_while(|b| b.is_ascii_whitespace()).count();
+    let mut i = start;
+    let negative = match arg.get(i) {
+        Some(b'-') => {
+            i += 1;
+            true
+        }
+        Some(b'+') => {
+            i += 1;
+            false
+        }
+        _ => false,
+    };
+    let radix = match (arg.get(i), arg.get(i + 1), arg.get(i + 2)) {
+        (Some(b'0'), Some(b'x'), Some(c)) | (Some(b'0'), Some(b'X'), Some(c))
+            if c.is_ascii_hexdigit() =>
+        {
+            i += 2;
+            16
+        }
+        (Some(b'0'), _, _) => 8,
+        _ => 10,
+    };
+    let digits = arg[i..]
+        .iter()
+        .take_while(|b| (**b as char).is_digit(radix))
+        .count();
+    let mut magnitude: u128 = 0;
+    let mut overflow = false;
+    for &b in &arg[i..i + digits] {
+        let digit = (b as char).to_digit(radix).unwrap() as u128;
+        magnitude = magnitude * radix as u128 + digit;
+        if magnitude > u64::MAX as u128 {
+            overflow = true;
+            magnitude = u64::MAX as u128 + 1;
+        }
+    }
+    // with no digits nothing was converted, not even a sign or blanks
+    let end = if digits == 0 { 0 } else { i + digits };
+    let value = if negative {
+        -(magnitude as i128)
+    } else {
+        magnitude as i128
+    };
+    let (value, overflow) = if unsigned {
+        if overflow {
+            (u64::MAX as i128, true)
+        } else {
+            (value.rem_euclid(1 << 64), false)
+        }
+    } else if value > i64::MAX as i128 {
+        (i64::MAX as i128, true)
+    } else if value < i64::MIN as i128 {
+        (i64::MIN as i128, true)
+    } else {
+        (value, false)
+    };
+    if overflow {
+        errors.push(format!("{}: Numerical result out of range", quote(arg)));
+        return (value, false);
+    }
+    (value, check_end(arg, digits > 0, end, errors))
+}
+
+/// `arg` as a floating point number, for `%f` and friends, with the
+/// forms `strtod` takes: decimal or hexadecimal, `inf` and `nan`.
+fn parse_float(arg: &[u8], errors: &mut Vec<String>) -> (f64, bool) {
+    if let Some(value) = char_constant(arg, errors) {
+        return (value as f64, true);
+    }
+    let start = arg.iter().take_while(|b| b.is_ascii_whitespace()).count();
+    let text = &arg[start..];
+    let candidate = text
+        .iter()
+        .take_while(|b| b.is_ascii_alphanumeric() || b"+-.".contains(b))
+        .count();
+    // the longest prefix that is a number
+    let mut parsed = None;
+    for len in (1..=candidate).rev() {
+        let s = std::str::from_utf8(&text[..len]).unwrap();
+        if let Some(value) = parse_hex_float(s).or_else(|| s.parse::<f64>().ok()) {
+            parsed = Some((value, len));
+            break;
+        }
+    }
+    match parsed {
+        Some((value, len)) => (value, check_end(arg, true, start + len, errors)),
+        None => (0.0, check_end(arg, false, 0, errors)),
+    }
+}
+
+/// A C99 hexadecimal floating point number such as `0x1.8p3`.
+fn parse_hex_float(s: &str) -> Option<f64> {
+    let (negative, s) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+    if !(s.starts_with("0x") || s.starts_with("0X")) {
+        return None;
+    }
+    let s = &s[2..];
+    let (mantissa, exponent) = match s.find(['p', 'P']) {
+        Some(p) => (&s[..p], s[p + 1..].parse::<i32>().ok()?),
+        None => (s, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if int.is_empty() && frac.is_empty() {
+        return None;
+    }
+    let mut value = 0.0;
+    for c in int.chars() {
+        value = value * 16.0 + c.to_digit(16)? as f64;
+    }
+    let mut scale = 1.0 / 16.0;
+    for c in frac.chars() {
+        value += c.to_digit(16)? as f64 * scale;
+        scale /= 16.0;
+    }
+    let value = value * 2f64.powi(exponent);
+    Some(if negative { -value } else { value })
+}
+
+/// The value of a character constant such as `'a`, warning if more
+/// follows it.
+fn char_constant(arg: &[u8], errors: &mut Vec<String>) -> Option<u8> {
+    match arg {
+        [b'\'', c, rest @ ..] | [b'"', c, rest @ ..] => {
+            if !rest.is_empty() {
+                errors.push(format!(
+                    "warning: {}: character(s) following character constant have been ignored",
+                    String::from_utf8_lossy(rest)
+                ));
+            }
+            Some(*c)
+        }
+        _ => None,
+    }
+}
+
+/// Report `arg` if the number in it ended before `end`. Returns false if
+/// it did.
+fn check_end(arg: &[u8], converted: bool, end: usize, errors: &mut Vec<String>) -> bool {
+    if end >= arg.len() {
+        return true;
+    }
+    errors.push(if converted {
+        format!("{}: value not completely converted", quote(arg))
+    } else {
+        format!("{}: expected a numeric value", quote(arg))
+    });
+    false
+}
+
+/// `s` quoted for reuse as shell input, as `%q` does: left alone if it
+/// needs no quoting, in double quotes if it only has a `'` to protect,
+/// otherwise in single quotes with control bytes written as `$'\t'`.
+fn shell_quote(s: &[u8]) -> Vec<u8> {
+    if s.is_empty() {
+        return b"''".to_vec();
+    }
+    let safe = |i: usize, b: u8| {
+        b.is_ascii_alphanumeric() || b"%+,-./:@_".contains(&b) || (i > 0 && b"~#".contains(&b))
+    };
+    if s.iter().enumerate().all(|(i, &b)| safe(i, b)) {
+        return s.to_vec();
+    }
+    let printable = |b: u8| (0x20..0x7f).contains(&b);
+    if s.contains(&b'\'') && s.iter().all(|&b| printable(b) && !b"\"$`\\!".contains(&b)) {
+        let mut out = vec![b'"'];
+        out.extend_from_slice(s);
+        out.push(b'"');
+        return out;
+    }
+    let mut out = vec![b'\''];
+    // inside a $'...' part
+    let mut dollar = false;
+    for &b in s {
+        if printable(b) {
+            if dollar {
+                out.extend_from_slice(b"''");
+                dollar = false;
+            }
+            if b == b'\'' {
+                out.extend_from_slice(b"'\\''");
+            } else {
+                out.push(b);
+            }
+            continue;
+        }
+        if !dollar {
+            out.extend_from_slice(b"'$'");
+            dollar = true;
+        }
+        match b {
+            0x07 => out.extend_from_slice(b"\\a"),
+            0x08 => out.extend_from_slice(b"\\b"),
+            b'\t' => out.extend_from_slice(b"\\t"),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            0x0b => out.extend_from_slice(b"\\v"),
+            0x0c => out.extend_from_slice(b"\\f"),
+            b'\r' => out.extend_from_slice(b"\\r"),
+            _ => out.extend_from_slice(format!("\\{:03o}", b).as_bytes()),
+        }
+    }
+    out.push(b'\'');
+    out
+}
+
+/// Append `sign`, `prefix` and `body` to `out`, padded to the width of
+/// `spec`: with zeros after the prefix if `zero`, otherwise with spaces.
+fn pad(out: &mut Vec<u8>, spec: &Spec, zero: bool, prefix: &[u8], body: &[u8]) {
+    let len = prefix.len() + body.len();
+    let fill = spec.width.saturating_sub(len);
+    if spec.left {
+        out.extend_from_slice(prefix);
+        out.extend_from_slice(body);
+        out.resize(out.len() + fill, b' ');
+    } else if zero {
+        out.extend_from_slice(prefix);
+        out.resize(out.len() + fill, b'0');
+        out.extend_from_slice(body);
+    } else {
+        out.resize(out.len() + fill, b' ');
+        out.extend_from_slice(prefix);
+        out.extend_from_slice(body);
+    }
+}
+
+fn sign(negative: bool, spec: &Spec) -> &'static [u8] {
+    if negative {
+        b"-"
+    } else if spec.plus {
+        b"+"
+    } else if spec.space {
+        b" "
+    } else {
+        b""
+    }
+}
+
+/// `%d %i %o %u %x %X` of `value`.
+fn format_int(out: &mut Vec<u8>, conversion: u8, spec: &Spec, value: i128) {
+    let magnitude = value.unsigned_abs();
+    let mut digits = match conversion {
+        b'o' => format!("{:o}", magnitude),
+        b'x' => format!("{:x}", magnitude),
+        b'X' => format!("{:X}", magnitude),
+        _ => format!("{}", magnitude),
+    };
+    if let Some(precision) = spec.precision {
+        if precision == 0 && magnitude == 0 {
+            digits.clear();
+        } else if digits.len() < precision {
+            digits = format!("{}{}", "0".repeat(precision - digits.len()), digits);
+        }
+    }
+    let prefix: &[u8] = match conversion {
+        b'd' | b'i' => sign(value < 0, spec),
+        b'o' if spec.alt && !digits.starts_with('0') => b"0",
+        b'x' if spec.alt && magnitude != 0 => b"0x",
+        b'X' if spec.alt && magnitude != 0 => b"0X",
+        _ => b"",
+    };
+    let zero = spec.zero && spec.precision.is_none();
+    pad(out, spec, zero, prefix, digits.as_bytes());
+}
+
+/// `value` in `%e` style with `precision` digits after the point.
+fn exponential(value: f64, precision: usize, alt: bool) -> String {
+    let s = format!("{:.*e}", precision, value);
+    let (mantissa, exponent) = s.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    format!(
+        "{}{}e{}{:02}",
+        mantissa,
+        if alt && precision == 0 { "." } else { "" },
+        if exponent < 0 { '-' } else { '+' },
+        exponent.abs()
+    )
+}
+
+/// `%f %F %e %E %g %G` of `value`.
+fn format_float(out: &mut Vec<u8>, conversion: u8, spec: &Spec, value: f64) {
+    let prefix = sign(value.is_sign_negative(), spec);
+    let magnitude = value.abs();
+    let upper = conversion.is_ascii_uppercase();
+    if !magnitude.is_finite() {
+        let body = if magnitude.is_nan() { "nan" } else { "inf" };
+        let body = if upper {
+            body.to_uppercase()
+        } else {
+            body.to_string()
+        };
+        pad(out, spec, false, prefix, body.as_bytes());
+        return;
+    }
+    let precision = spec.precision.unwrap_or(6);
+    let body = match conversion.to_ascii_lowercase() {
+        b'f' => {
+            let mut s = format!("{:.*}", precision, magnitude);
+            if spec.alt && precision == 0 {
+                s.push('.');
+            }
+            s
+        }
+        b'e' => exponential(magnitude, precision, spec.alt),
+        _ => {
+            let precision = precision.max(1);
+            let exponent: i32 = if magnitude == 0.0 {
+                0
+            } else {
+                let s = format!("{:.*e}", precision - 1, magnitude);
+                s.split_once('e').unwrap().1.parse().unwrap()
+            };
+            let mut s = if exponent < precision as i32 && exponent >= -4 {
+                let mut s = format!(
+                    "{:.*}",
+                    (precision as i32 - 1 - exponent) as usize,
+                    magnitude
+                );
+                if spec.alt && !s.contains('.') {
+                    s.push('.');
+                }
+                s
+            } else {
+                exponential(magnitude, precision - 1, spec.alt)
+            };
+            if !spec.alt {
+                let e = s.find('e').unwrap_or(s.len());
+                let (number, exponent) = s.split_at(e);
+                let number = if number.contains('.') {
+                    number.trim_end_matches('0').trim_end_matches('.')
+                } else {
+                    number
+                };
+                s = format!("{}{}", number, exponent);
+            }
+            s
+        }
+    };
+    let body = if upper { body.to_uppercase() } else { body };
+    pad(out, spec, spec.zero, prefix, body.as_bytes());
+}
+
+impl Printf {
+    /// Parse command line arguments: FORMAT and its ARGUMENTs. `--help`
+    /// and `--version` count only as the sole argument, and a leading
+    /// `--` is skipped.
+    pub fn parse<I, S>(&mut self, args: I) -> Result<(), String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<std::ffi::OsStr>,
+    {
+        let mut args: Vec<OsString> = args
+            .into_iter()
+            .map(|arg| arg.as_ref().to_os_string())
+            .collect();
+        if args.len() == 1 {
+            match args[0].to_str() {
+                // display this help and exit
+                Some("--help") => {
+                    self.option.display_help = true;
+                    return Ok(());
+                }
+                // output version information and exit
+                Some("--version") => {
+                    self.option.display_version = true;
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+        if args.first().map(|arg| arg == "--").unwrap_or(false) {
+            args.remove(0);
+        }
+        if args.is_empty() {
+            return Err("printf: missing operand\n\
+                        Try 'printf --help' for more information."
+                .to_string());
+        }
+        self.format = cliopt::os_bytes(&args[0]).into_owned();
+        self.args = args[1..]
+            .iter()
+            .map(|arg| cliopt::os_bytes(arg).into_owned())
+            .collect();
+        Ok(())
+    }
+
+    /// Format `args` by `format`, reusing it while arguments remain.
+    /// Stops at the first error in `format`, which is returned after what
+    /// was output up to it.
+    fn format(&self, format: &[u8], args: &[Vec<u8>]) -> (Output, Option<String>) {
+        let mut output = Output {
+            out: Vec::new(),
+            errors: Vec::new(),
+            failed: false,
+        };
+        let mut next = 0;
+        loop {
+            match self.pass(format, &args[next..], &mut output) {
+                Ok(Pass::Done(used)) => {
+                    next += used;
+                    if used == 0 || next >= args.len() {
+                        break;
+                    }
+                }
+                Ok(Pass::Stop) => return (output, None),
+                Err(err) => return (output, Some(err)),
+            }
+        }
+        if next < args.len() {
+            output.errors.push(format!(
+                "warning: ignoring excess arguments, starting with {}",
+                quote(&args[next])
+            ));
+        }
+        (output, None)
+    }
+
+    /// One pass over `format`.
+    fn pass(&self, format: &[u8], args: &[Vec<u8>], output: &mut Output) -> Result<Pass, String> {
+        let mut args = args.iter();
+        let mut used = 0;
+        let mut next_arg = |used: &mut usize| -> Option<&[u8]> {
+            let arg = args.next()?;
+            *used += 1;
+            Some(arg.as_slice())
+        };
+        let mut i = 0;
+        while i < format.len() {
+            match format[i] {
+                b'\\' if i + 1 < format.len() => {
+                    match unescape_one(&format[i + 1..], false, &mut output.out)? {
+                        Some(n) => i += 1 + n,
+                        None => return Ok(Pass::Stop),
+                    }
+                    continue;
+                }
+                b'%' => {}
+                b => {
+                    output.out.push(b);
+                    i += 1;
+                    continue;
+                }
+            }
+            let start = i;
+            i += 1;
+            match format.get(i) {
+                Some(b'%') => {
+                    output.out.push(b'%');
+                    i += 1;
+                    continue;
+                }
+                Some(b'b') => {
+                    i += 1;
+                    let arg = next_arg(&mut used).unwrap_or(b"");
+                    let mut j = 0;
+                    while j < arg.len() {
+                        if arg[j] != b'\\' || j + 1 == arg.len() {
+                            output.out.push(arg[j]);
+                            j += 1;
+                            continue;
+                        }
+                        match unescape_one(&arg[j + 1..], true, &mut output.out)? {
+                            Some(n) => j += 1 + n,
+                            None => return Ok(Pass::Stop),
+                        }
+                    }
+                    continue;
+                }
+                Some(b'q') => {
+                    i += 1;
+                    if let Some(arg) = next_arg(&mut used) {
+                        output.out.extend_from_slice(&shell_quote(arg));
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+
+            let mut spec = Spec::default();
+            while let Some(&flag) = format.get(i) {
+                match flag {
+                    b'-' => spec.left = true,
+                    b'+' => spec.plus = true,
+                    b' ' => spec.space = true,
+                    b'#' => spec.alt = true,
+                    b'0' => spec.zero = true,
+                    // thousands grouping, which the C locale does not do
+                    b'\'' | b'I' => {}
+                    _ => break,
+                }
+                i += 1;
+            }
+            let mut number = |i: &mut usize, used: &mut usize, what: &str| -> Result<i64, String> {
+                if format.get(*i) == Some(&b'*') {
+                    *i += 1;
+                    let arg = next_arg(used).unwrap_or(b"");
+                    let (value, converted) = parse_int(arg, false, &mut output.errors);
+                    output.failed |= !converted;
+                    if value.abs() > i32::MAX as i128 {
+                        return Err(format!("invalid {}: {}", what, quote(arg)));
+                    }
+                    return Ok(value as i64);
+                }
+                let digits = format[*i..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_digit())
+                    .count();
+                let text = &format[*i..*i + digits];
+                *i += digits;
+                match std::str::from_utf8(text).unwrap().parse::<i32>() {
+                    Ok(value) => Ok(value as i64),
+                    Err(_) if digits == 0 => Ok(0),
+                    Err(_) => Err(format!("invalid {}: {}", what, quote(text))),
+                }
+            };
+            let width = number(&mut i, &mut used, "field width")?;
+            if width < 0 {
+                spec.left = true;
+            }
+            spec.width = width.unsigned_abs() as usize;
+            let has_precision = format.get(i) == Some(&b'.');
+            if has_precision {
+                i += 1;
+                let precision = number(&mut i, &mut used, "precision")?;
+                spec.precision = if precision < 0 {
+                    None
+                } else {
+                    Some(precision as usize)
+                };
+            }
+            while let Some(b'h' | b'l' | b'L' | b'j' | b't' | b'z') = format.get(i) {
+                i += 1;
+            }
+            // as in C, not every conversion takes every flag
+            let ok = |c: u8| {
+                b"cdeEfFgGiosuxX".contains(&c)
+                    && !(spec.alt && b"cdisu".contains(&c))
+                    && !(spec.zero && b"cs".contains(&c))
+                    && !(has_precision && c == b'c')
+            };
+            let conversion = match format.get(i) {
+                Some(&c) if ok(c) => c,
+                Some(_) => {
+                    return Err(format!(
+                        "{}: invalid conversion specification",
+                        String::from_utf8_lossy(&format[start..i + 1])
+                    ))
+                }
+                None => {
+                    return Err(format!(
+                        "{}: invalid conversion specification",
+                        String::from_utf8_lossy(&format[start..])
+                    ))
+                }
+            };
+            i += 1;
+
+            let arg = next_arg(&mut used);
+            match conversion {
+                b'c' => {
+                    let byte = arg.and_then(|arg| arg.first()).cloned().unwrap_or(0);
+                    pad(&mut output.out, &spec, false, b"", &[byte]);
+                }
+                b's' => {
+                    let arg = arg.unwrap_or(b"");
+                    let len = spec.precision.unwrap_or(arg.len()).min(arg.len());
+                    pad(&mut output.out, &spec, false, b"", &arg[..len]);
+                }
+                b'd' | b'i' | b'o' | b'u' | b'x' | b'X' => {
+                    let signed = conversion == b'd' || conversion == b'i';
+                    let (value, converted) =
+                        parse_int(arg.unwrap_or(b""), !signed, &mut output.errors);
+                    output.failed |= !converted;
+                    format_int(&mut output.out, conversion, &spec, value);
+                }
+                _ => {
+                    let (value, converted) = parse_float(arg.unwrap_or(b""), &mut output.errors);
+                    output.failed |= !converted;
+                    format_float(&mut output.out, conversion, &spec, value);
+                }
+            }
+        }
+        Ok(Pass::Done(used))
+    }
+
+    /// Format the parsed FORMAT and ARGUMENTs to `out`, with diagnostics
+    /// to `err`. Returns the exit status.
+    pub fn run<W: Write + ?Sized, E: Write + ?Sized>(
+        &self,
+        out: &mut W,
+        err: &mut E,
+    ) -> io::Result<i32> {
+        let (output, fatal) = self.format(&self.format, &self.args);
+        out.write_all(&output.out)?;
+        out.flush()?;
+        for message in output.errors.iter().chain(fatal.iter()) {
+            writeln!(err, "printf: {}", message)?;
+        }
+        Ok(if output.failed || fatal.is_some() {
+            1
+        } else {
+            0
+        })
+    }
+
+    fn help<W: Write + ?Sized>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(
+            out,
+            "Usage: printf FORMAT [ARGUMENT]...\n  \
+             or:  printf OPTION\n\
+             Print ARGUMENT(s) according to FORMAT, or execute according to OPTION:\n\n      \
+             --help     display this help and exit\n      \
+             --version  output version information and exit\n\n\
+             FORMAT controls the output as in C printf.  Interpreted sequences are:\n\n  \
+             \\\"      double quote\n  \
+             \\\\      backslash\n  \
+             \\a      alert (BEL)\n  \
+             \\b      backspace\n  \
+             \\c      produce no further output\n  \
+             \\e      escape\n  \
+             \\f      form feed\n  \
+             \\n      new line\n  \
+             \\r      carriage return\n  \
+             \\t      horizontal tab\n  \
+             \\v      vertical tab\n  \
+             \\NNN    byte with octal value NNN (1 to 3 digits)\n  \
+             \\xHH    byte with hexadecimal value HH (1 to 2 digits)\n  \
+             \\uHHHH  Unicode character with hex value HHHH (4 digits)\n  \
+             \\UHHHHHHHH  Unicode character with hex value HHHHHHHH (8 digits)\n  \
+             %%      a single %\n  \
+             %b      ARGUMENT as a string with '\\' escapes interpreted,\n          \
+             except that octal escapes are of the form \\0 or \\0NNN\n  \
+             %q      ARGUMENT is printed in a format that can be reused as shell input,\n          \
+             escaping non-printable characters with the proposed POSIX $'' syntax.\n\n\
+             and all C format specifications ending with one of diouxXfeEgGcs, with\n\
+             ARGUMENTs converted to proper type first.  Variable widths are handled."
+        )
+    }
+
+    fn version<W: Write + ?Sized>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "rust printf version 0.1.0")
+    }
+}
+
+impl Utility for Printf {
+    fn name(&self) -> &str {
+        "printf"
+    }
+
+    fn parse_args(&mut self, args: &[OsString]) -> Result<(), String> {
+        self.parse(args)
+    }
+
+    fn execute(&mut self, ctx: &mut Context) -> i32 {
+        let result = if self.option.display_help {
+            self.help(&mut ctx.stdout).map(|_| 0)
+        } else if self.option.display_version {
+            self.version(&mut ctx.stdout).map(|_| 0)
+        } else {
+            self.run(&mut ctx.stdout, &mut ctx.stderr)
+        };
+        result.unwrap_or_else(|err| {
+            writeln!(ctx.stderr, "printf: write error: {}", describe(&err)).unwrap();
+            1
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    fn printf(args: &[&str]) -> (i32, Vec<u8>, String) {
+        let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+        let mut stdin: &[u8] = b"";
+        let mut out: Vec<u8> = Vec::new();
+        let mut err: Vec<u8> = Vec::new();
+        let status = {
+            let mut ctx = Context::new(&mut stdin, &mut out, &mut err);
+            PrintfBuilder::new().build().call(&args, &mut ctx)
+        };
+        (status, out, String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        {
+            let mut p = PrintfBuilder::new().build();
+            p.parse(["--", "%s\\n", "a", "--help"]).unwrap();
+            assert_eq!(PrintfOption::default(), p.option);
+            assert_eq!(p.format, b"%s\\n".to_vec());
+            assert_eq!(p.args, vec![b"a".to_vec(), b"--help".to_vec()]);
+        }
+
+        {
+            // "--help"
+            let expects = PrintfOption {
+                display_help: true,
+                ..Default::default()
+            };
+            let mut p = PrintfBuilder::new().build();
+            p.parse(["--help"]).unwrap();
+            assert_eq!(expects, p.option);
+        }
+
+        {
+            // "--version"
+            let expects = PrintfOption {
+                display_version: true,
+                ..Default::default()
+            };
+            let mut p = PrintfBuilder::new().build();
+            p.parse(["--version"]).unwrap();
+            assert_eq!(expects, p.option);
+        }
+
+        {
+            let mut p = PrintfBuilder::new().build();
+            assert_eq!(
+                p.parse(["--"]),
+                Err("printf: missing operand\n\
+                     Try 'printf --help' for more information."
+                    .to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_printf() {
+        let cases: &[&[&str]] = &[
+            &["plain\\n"],
+            &["\\\"\\q\\101\\0101\\x41\\x4g\\e\\a\\b\\f\\v\\r\\t\\\\\\"],
+            &["%%|%s|%5s|%-5s|%.2s|\\n", "a", "b", "c", "def"],
+            &["%c%c%c|%-3c|\\n", "hello", "", "x", "y"],
+            &["%d %i %o %u %x %X\\n", "-42", "42", "8", "42", "255", "255"],
+            &[
+                "%d %d %d %d %d %d\\n",
+                "0x1F",
+                "010",
+                " 7",
+                "+3",
+                "'a",
+                "\"b",
+            ],
+            &["%u %x %d\\n", "-1", "-1", "-9223372036854775808"],
+            &[
+                "%#x %#X %#o %#o %+d % d %05d %-5d|\\n",
+                "255",
+                "0",
+                "8",
+                "0",
+                "5",
+                "5",
+                "-42",
+                "3",
+            ],
+            &[
+                "%.3d|%.0d|%5.3x|%*d|%-*d|%.*s|%*d|\\n",
+                "7",
+                "0",
+                "10",
+                "5",
+                "1",
+                "4",
+                "2",
+                "2",
+                "abc",
+                "-3",
+                "9",
+            ],
+            &[
+                "%f %.2f %5.1f %-8.3f| %010.3f %+.0f %#.0f\\n",
+                "3.14159",
+                "2.675",
+                "9.96",
+                "1",
+                "-3.14159",
+                "2.5",
+                "3",
+            ],
+            &[
+                "%e %.0e %E %-12.3e| %#.0e\\n",
+                "1234.5",
+                "12345",
+                "0.000123",
+                "-5",
+                "7",
+            ],
+            &[
+                "%g %g %g %g %g %G %#g %.0g %.10g\\n",
+                "0.0001",
+                "1e-5",
+                "123456789",
+                "100000",
+                "0.1",
+                "1e100",
+                "2",
+                "5.5",
+                "3.14159265358979",
+            ],
+            &[
+                "%f %g %e %F %5.1f| %05f|\\n",
+                "inf",
+                "-inf",
+                "nan",
+                "inf",
+                "nan",
+                "-inf",
+            ],
+            &["%f %f %f\\n", "0x10", "0x1.8p3", " .5"],
+            &["%ld %hhd %lld %jd %zd %Lf\\n", "1", "2", "3", "4", "5", "6"],
+            &["%b|%b|%b|%b\\n", "a\\0101", "\\101\\8", "\\\"\\t", ""],
+            &[
+                "%q %q %q %q %q %q %q\\n",
+                "plain",
+                "a b",
+                "",
+                "it's",
+                "x$y",
+                "a'b$c",
+                "~x",
+            ],
+            &["%q %q %q %q\\n", "a\tb", "\u{1}x", "x\u{1b}", "a\t\tb"],
+            &["%s=%d\\n", "a", "1", "b", "2", "c"],
+            &["%s|%d|%f|%c|%b|%q|\\n"],
+            &["keep\\c going %s", "x"],
+            &["%s %b %s\\n", "a", "b\\cc", "d"],
+            // conversion errors: reported, but formatting goes on
+            &[
+                "%d %d %d %d|\\n",
+                "abc",
+                "12abc",
+                "99999999999999999999",
+                "'ab",
+            ],
+            &["%i %f %x %d\\n", "1.5", "abc", "08", "'"],
+            &["%d\\n", "x'y", "a\\b", "é"],
+            // a sign or blanks alone are not a number
+            &["%d|%d|%d|%o|%u|\\n", "-", "+", "  ", "-", "-"],
+            // format errors: fatal
+            &["%z"],
+            &["a%"],
+            &["[%5b]", "x"],
+            &["[%05s]", "x"],
+            &["[%#d]", "1"],
+            &["[%.2c]", "x"],
+            &["[%6q]", "x"],
+            &["[%5%]"],
+            &["a\\x"],
+            &["\\u12"],
+            &["\\ud800"],
+            &["%b", "\\x"],
+            &["no conversions\\n", "extra", "args"],
+            &["--", "%s\\n", "x"],
+            &[],
+        ];
+        for args in cases.iter() {
+            let (status, out, err) = printf(args);
+            let expects = process::Command::new("/usr/bin/printf")
+                .args(args.iter())
+                .output()
+                .expect("Failed to execute command");
+            let expects_err = String::from_utf8(expects.stderr)
+                .unwrap()
+                .replace("/usr/bin/printf", "printf");
+            assert_eq!(out, expects.stdout, "printf {:?}", args);
+            assert_eq!(err, expects_err, "printf {:?}", args);
+            assert_eq!(Some(status), expects.status.code(), "printf {:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_unicode() {
+        // GNU printf leaves these alone outside a UTF-8 locale
+        assert_eq!(
+            printf(&["\\u00e9\\U0001F600|%b", "\\u263a"]),
+            (
+                0,
+                "\u{e9}\u{1f600}|\u{263a}".as_bytes().to_vec(),
+                String::new()
+            )
+        );
+        assert_eq!(
+            printf(&["\\U0000d800"]),
+            (
+                1,
+                Vec::new(),
+                "printf: invalid universal character name \\U0000d800\n".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        let cases: &[(&[u8], &[u8])] = &[
+            (b"", b"''"),
+            (b"a,b=c", b"'a,b=c'"),
+            (b"x~#", b"x~#"),
+            (b"it's", b"\"it's\""),
+            (b"it's $HOME", b"'it'\\''s $HOME'"),
+            (b"\xc3\xa9", b"''$'\\303\\251'"),
+            (b"a\nb", b"'a'$'\\n''b'"),
+        ];
+        for &(s, expects) in cases {
+            assert_eq!(
+                String::from_utf8_lossy(&shell_quote(s)),
+                String::from_utf8_lossy(expects)
+            );
+        }
+    }
+}
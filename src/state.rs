@@ -0,0 +1,233 @@
+//! A snapshot of `Cat`'s running state, so that a restarted process can
+//! carry on numbering where the last one stopped.
+use super::cliopt::os_bytes;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const HEADER: &str = "rtw-cat-state 1";
+
+/// Everything `Cat` needs to continue an interrupted run: the line
+/// counters, whether the output stopped in the middle of a line, and how
+/// far each input file has been read.
+///
+/// The text form (`to_string` / `parse`) is one `key value` pair per line
+/// and is stable across versions of this format.
+#[derive(Clone, PartialEq, Debug)]
+pub struct CatState {
+    /// the number `-n`/`-b` gives the next numbered line
+    pub number: u32,
+    /// blank lines seen in a row, for `-s`
+    pub blank: u32,
+    pub at_line_start: bool,
+    /// bytes of each input file read so far, in the order first seen
+    pub offsets: Vec<(PathBuf, u64)>,
+}
+
+impl Default for CatState {
+    fn default() -> CatState {
+        CatState {
+            number: 1,
+            blank: 0,
+            at_line_start: true,
+            offsets: Vec::new(),
+        }
+    }
+}
+
+impl CatState {
+    /// Bytes of `path` read so far, 0 for a file not seen before.
+    pub fn offset(&self, path: &Path) -> u64 {
+        self.offsets
+            .iter()
+            .find(|(p, _)| p == path)
+            .map_or(0, |&(_, offset)| offset)
+    }
+
+    pub fn set_offset(&mut self, path: &Path, offset: u64) {
+        match self.offsets.iter_mut().find(|(p, _)| p == path) {
+            Some(entry) => entry.1 = offset,
+            None => self.offsets.push((path.to_path_buf(), offset)),
+        }
+    }
+}
+
+/// Write a path so that it fits on one line and survives any bytes:
+/// backslash, newline and bytes outside UTF-8 are escaped.
+fn escape_path(path: &Path, f: &mut fmt::Formatter) -> fmt::Result {
+    for chunk in os_bytes(path.as_os_str()).utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                c => write!(f, "{}", c)?,
+            }
+        }
+        for b in chunk.invalid() {
+            write!(f, "\\x{:02x}", b)?;
+        }
+    }
+    Ok(())
+}
+
+fn unescape_path(text: &str) -> Option<PathBuf> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        rest = tail;
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+        match rest.split_first()? {
+            (b'\\', tail) => {
+                bytes.push(b'\\');
+                rest = tail;
+            }
+            (b'n', tail) => {
+                bytes.push(b'\n');
+                rest = tail;
+            }
+            (b'x', tail) if tail.len() >= 2 => {
+                let hex = std::str::from_utf8(&tail[..2]).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                rest = &tail[2..];
+            }
+            _ => return None,
+        }
+    }
+    path_from_bytes(bytes)
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> Option<PathBuf> {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+    Some(PathBuf::from(OsString::from_vec(bytes)))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> Option<PathBuf> {
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+impl fmt::Display for CatState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "number {}", self.number)?;
+        writeln!(f, "blank {}", self.blank)?;
+        writeln!(f, "at-line-start {}", self.at_line_start)?;
+        for (path, offset) in &self.offsets {
+            write!(f, "offset {} ", offset)?;
+            escape_path(path, f)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// The text given to `CatState::from_str` is not a saved state.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ParseStateError {
+    line: usize,
+}
+
+impl fmt::Display for ParseStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid state at line {}", self.line)
+    }
+}
+
+impl std::error::Error for ParseStateError {}
+
+impl FromStr for CatState {
+    type Err = ParseStateError;
+
+    fn from_str(text: &str) -> Result<CatState, ParseStateError> {
+        let mut state = CatState::default();
+        let mut lines = text.lines().enumerate();
+        match lines.next() {
+            Some((_, HEADER)) => (),
+            _ => return Err(ParseStateError { line: 1 }),
+        }
+        for (i, line) in lines {
+            let error = ParseStateError { line: i + 1 };
+            let (key, value) = line.split_once(' ').ok_or(error.clone())?;
+            match key {
+                "number" => state.number = value.parse().map_err(|_| error)?,
+                "blank" => state.blank = value.parse().map_err(|_| error)?,
+                "at-line-start" => state.at_line_start = value.parse().map_err(|_| error)?,
+                "offset" => {
+                    let (offset, path) = value.split_once(' ').ok_or(error.clone())?;
+                    let offset = offset.parse().map_err(|_| error.clone())?;
+                    let path = unescape_path(path).ok_or(error)?;
+                    state.set_offset(&path, offset);
+                }
+                // keys from newer versions are ignored
+                _ => (),
+            }
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut state = CatState {
+            number: 42,
+            blank: 1,
+            at_line_start: false,
+            offsets: Vec::new(),
+        };
+        state.set_offset(Path::new("/var/log/app.log"), 1234);
+        state.set_offset(Path::new("odd\\name\nwith newline"), 7);
+        state.set_offset(Path::new("/var/log/app.log"), 2048);
+
+        let text = state.to_string();
+        assert_eq!(
+            text,
+            "rtw-cat-state 1\n\
+             number 42\n\
+             blank 1\n\
+             at-line-start false\n\
+             offset 2048 /var/log/app.log\n\
+             offset 7 odd\\\\name\\nwith newline\n"
+        );
+        assert_eq!(text.parse::<CatState>(), Ok(state.clone()));
+        assert_eq!(state.offset(Path::new("/var/log/app.log")), 2048);
+        assert_eq!(state.offset(Path::new("other")), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_path() {
+        let mut state = CatState::default();
+        state.set_offset(&path_from_bytes(b"a\xffb".to_vec()).unwrap(), 3);
+        let text = state.to_string();
+        assert!(text.ends_with("offset 3 a\\xffb\n"));
+        assert_eq!(text.parse::<CatState>(), Ok(state));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!("".parse::<CatState>(), Err(ParseStateError { line: 1 }));
+        assert_eq!(
+            "rtw-cat-state 1\nnumber x\n".parse::<CatState>(),
+            Err(ParseStateError { line: 2 })
+        );
+        assert_eq!(
+            "rtw-cat-state 1\nnumber 3\noffset 5 bad\\q\n".parse::<CatState>(),
+            Err(ParseStateError { line: 3 })
+        );
+        assert_eq!(
+            "rtw-cat-state 1\nfuture 9\nnumber 3\n"
+                .parse::<CatState>()
+                .map(|s| s.number),
+            Ok(3)
+        );
+    }
+}
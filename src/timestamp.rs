@@ -0,0 +1,343 @@
+//! Line timestamps in the style of moreutils `ts`.
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TimestampMode {
+    /// wall-clock time of each line
+    Absolute,
+    /// time elapsed since the start
+    Relative,
+    /// time elapsed since the previous line
+    Delta,
+}
+
+impl TimestampMode {
+    /// The format used when none is given, as in `ts`.
+    pub fn default_format(self) -> &'static str {
+        match self {
+            TimestampMode::Absolute => "%b %d %H:%M:%S",
+            TimestampMode::Relative | TimestampMode::Delta => "%H:%M:%S",
+        }
+    }
+}
+
+pub struct Timestamper {
+    mode: TimestampMode,
+    format: String,
+    start: SystemTime,
+    last: Option<SystemTime>,
+}
+
+impl Timestamper {
+    pub fn new(mode: TimestampMode, format: &str, start: SystemTime) -> Timestamper {
+        Timestamper {
+            mode,
+            format: format.to_string(),
+            start,
+            last: None,
+        }
+    }
+
+    /// Append the timestamp of a line seen at `now` to `out`.
+    pub fn stamp(&mut self, now: SystemTime, out: &mut Vec<u8>) {
+        let tm = match self.mode {
+            TimestampMode::Absolute => Tm::local(now),
+            TimestampMode::Relative => Tm::utc(elapsed(self.start, now)),
+            TimestampMode::Delta => Tm::utc(elapsed(self.last.unwrap_or(self.start), now)),
+        };
+        self.last = Some(now);
+        format(&self.format, &tm, out);
+    }
+}
+
+fn elapsed(from: SystemTime, to: SystemTime) -> Duration {
+    to.duration_since(from).unwrap_or_default()
+}
+
+/// Broken-down time, like C's `struct tm`.
+#[derive(Debug, PartialEq)]
+struct Tm {
+    epoch: i64,
+    nanos: u32,
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    /// 0 is Sunday
+    weekday: u32,
+    /// 1-based day of the year
+    yearday: u32,
+    offset: i64,
+    zone: String,
+}
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = year - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Proleptic Gregorian date of a day count since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+impl Tm {
+    fn new(epoch: i64, nanos: u32, offset: i64, zone: String) -> Tm {
+        let local = epoch + offset;
+        let days = local.div_euclid(86_400);
+        let secs = local.rem_euclid(86_400) as u32;
+        let (year, month, day) = civil_from_days(days);
+        Tm {
+            epoch,
+            nanos,
+            year,
+            month,
+            day,
+            hour: secs / 3600,
+            minute: secs / 60 % 60,
+            second: secs % 60,
+            weekday: (days + 4).rem_euclid(7) as u32,
+            yearday: (days - days_from_civil(year, 1, 1) + 1) as u32,
+            offset,
+            zone,
+        }
+    }
+
+    fn utc(since_epoch: Duration) -> Tm {
+        Tm::new(
+            since_epoch.as_secs() as i64,
+            since_epoch.subsec_nanos(),
+            0,
+            "UTC".to_string(),
+        )
+    }
+
+    fn local(time: SystemTime) -> Tm {
+        let (epoch, nanos) = match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+            Err(e) => {
+                let d = e.duration();
+                let nanos = d.subsec_nanos();
+                if nanos == 0 {
+                    (-(d.as_secs() as i64), 0)
+                } else {
+                    (-(d.as_secs() as i64) - 1, 1_000_000_000 - nanos)
+                }
+            }
+        };
+        let (offset, zone) = local_offset(epoch);
+        Tm::new(epoch, nanos, offset, zone)
+    }
+}
+
+/// UTC offset in seconds and zone abbreviation of the local time zone.
+#[cfg(unix)]
+fn local_offset(epoch: i64) -> (i64, String) {
+    use std::ffi::CStr;
+    let t = epoch as libc::time_t;
+    // SAFETY: localtime_r only writes to the tm we own; tm_zone, when set,
+    // points to static time zone data.
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&t, &mut tm).is_null() {
+            return (0, "UTC".to_string());
+        }
+        let zone = if tm.tm_zone.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(tm.tm_zone).to_string_lossy().into_owned()
+        };
+        (tm.tm_gmtoff as i64, zone)
+    }
+}
+
+#[cfg(not(unix))]
+fn local_offset(_epoch: i64) -> (i64, String) {
+    (0, "UTC".to_string())
+}
+
+/// Format `tm` like strftime(3). `%.S`, `%.s` and `%.T` add microseconds
+/// as in `ts`; `%f` gives microseconds as in Python and `%N` nanoseconds
+/// as in date(1).
+fn format(fmt: &str, tm: &Tm, out: &mut Vec<u8>) {
+    let mut s = String::new();
+    let mut chars = fmt.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            s.push(c);
+            continue;
+        }
+        let mut spec = match chars.next() {
+            Some(spec) => spec,
+            None => {
+                s.push('%');
+                break;
+            }
+        };
+        let fraction = spec == '.';
+        if fraction {
+            spec = match chars.next() {
+                Some(spec @ 'S') | Some(spec @ 's') | Some(spec @ 'T') => spec,
+                other => {
+                    s.push_str("%.");
+                    if let Some(other) = other {
+                        s.push(other);
+                    }
+                    continue;
+                }
+            };
+        }
+        let hour12 = if tm.hour.is_multiple_of(12) {
+            12
+        } else {
+            tm.hour % 12
+        };
+        match spec {
+            'a' => s.push_str(&WEEKDAYS[tm.weekday as usize][..3]),
+            'A' => s.push_str(WEEKDAYS[tm.weekday as usize]),
+            'b' | 'h' => s.push_str(&MONTHS[tm.month as usize - 1][..3]),
+            'B' => s.push_str(MONTHS[tm.month as usize - 1]),
+            'd' => s.push_str(&format!("{:02}", tm.day)),
+            'e' => s.push_str(&format!("{:>2}", tm.day)),
+            'f' => s.push_str(&format!("{:06}", tm.nanos / 1000)),
+            'F' => s.push_str(&format!("{}-{:02}-{:02}", tm.year, tm.month, tm.day)),
+            'D' => s.push_str(&format!(
+                "{:02}/{:02}/{:02}",
+                tm.month,
+                tm.day,
+                tm.year.rem_euclid(100)
+            )),
+            'H' => s.push_str(&format!("{:02}", tm.hour)),
+            'I' => s.push_str(&format!("{:02}", hour12)),
+            'j' => s.push_str(&format!("{:03}", tm.yearday)),
+            'm' => s.push_str(&format!("{:02}", tm.month)),
+            'M' => s.push_str(&format!("{:02}", tm.minute)),
+            'N' => s.push_str(&format!("{:09}", tm.nanos)),
+            'p' => s.push_str(if tm.hour < 12 { "AM" } else { "PM" }),
+            'R' => s.push_str(&format!("{:02}:{:02}", tm.hour, tm.minute)),
+            'S' => s.push_str(&format!("{:02}", tm.second)),
+            's' => s.push_str(&tm.epoch.to_string()),
+            'T' => s.push_str(&format!("{:02}:{:02}:{:02}", tm.hour, tm.minute, tm.second)),
+            'u' => s.push_str(&(if tm.weekday == 0 { 7 } else { tm.weekday }).to_string()),
+            'w' => s.push_str(&tm.weekday.to_string()),
+            'y' => s.push_str(&format!("{:02}", tm.year.rem_euclid(100))),
+            'Y' => s.push_str(&tm.year.to_string()),
+            'z' => {
+                let sign = if tm.offset < 0 { '-' } else { '+' };
+                let offset = tm.offset.abs();
+                s.push_str(&format!(
+                    "{}{:02}{:02}",
+                    sign,
+                    offset / 3600,
+                    offset / 60 % 60
+                ));
+            }
+            'Z' => s.push_str(&tm.zone),
+            'n' => s.push('\n'),
+            't' => s.push('\t'),
+            '%' => s.push('%'),
+            other => {
+                s.push('%');
+                s.push(other);
+            }
+        }
+        if fraction {
+            s.push_str(&format!(".{:06}", tm.nanos / 1000));
+        }
+    }
+    out.extend_from_slice(s.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatted(fmt: &str, tm: &Tm) -> String {
+        let mut out = Vec::new();
+        format(fmt, tm, &mut out);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_calendar() {
+        for &(y, m, d) in &[(1970, 1, 1), (2000, 2, 29), (1969, 12, 31), (2038, 1, 19)] {
+            assert_eq!(civil_from_days(days_from_civil(y, m, d)), (y, m, d));
+        }
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+    }
+
+    #[test]
+    fn test_format() {
+        // 2009-02-13 23:31:30.5 UTC, a Friday
+        let tm = Tm::new(1_234_567_890, 500_000_000, 0, "UTC".to_string());
+        assert_eq!(formatted("%b %d %H:%M:%S", &tm), "Feb 13 23:31:30");
+        assert_eq!(
+            formatted("%F %T %z %Z %a %A %B %j %e %I%p %s", &tm),
+            "2009-02-13 23:31:30 +0000 UTC Fri Friday February 044 13 11PM 1234567890"
+        );
+        assert_eq!(
+            formatted("%.S %.T %S.%f %N %%%q", &tm),
+            "30.500000 23:31:30.500000 30.500000 500000000 %%q"
+        );
+
+        let tm = Tm::new(1_234_567_890, 0, -(5 * 3600 + 30 * 60), "X".to_string());
+        assert_eq!(formatted("%H:%M %z", &tm), "18:01 -0530");
+    }
+
+    #[test]
+    fn test_stamp() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let mut t = Timestamper::new(TimestampMode::Delta, "%.S", start);
+        let mut out = Vec::new();
+        t.stamp(start + Duration::from_millis(1500), &mut out);
+        out.push(b' ');
+        t.stamp(start + Duration::from_millis(1750), &mut out);
+        assert_eq!(out, b"01.500000 00.250000".to_vec());
+
+        let mut t = Timestamper::new(TimestampMode::Relative, "%T", start);
+        let mut out = Vec::new();
+        t.stamp(start + Duration::from_secs(3725), &mut out);
+        assert_eq!(out, b"01:02:05".to_vec());
+    }
+}
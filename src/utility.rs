@@ -0,0 +1,156 @@
+//! The interface shared by every tool in the crate, so that they can be
+//! run in-memory in tests and dispatched uniformly.
+use super::cat::describe;
+use super::vfs::{FileSystem, RealFs};
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Write};
+
+/// What a utility runs with: its standard streams, environment and the
+/// filesystem its FILE operands are found in.
+pub struct Context<'a> {
+    pub stdin: &'a mut dyn BufRead,
+    pub stdout: &'a mut dyn Write,
+    pub stderr: &'a mut dyn Write,
+    /// environment variables, as from `std::env::vars_os`
+    pub env: Vec<(OsString, OsString)>,
+    pub stdout_is_terminal: bool,
+    pub fs: &'a dyn FileSystem,
+}
+
+impl<'a> Context<'a> {
+    /// Standard streams with an empty environment, for running in-memory.
+    /// Files are those of the real filesystem until `fs` is replaced, and
+    /// standard output is none of them.
+    pub fn new(
+        stdin: &'a mut dyn BufRead,
+        stdout: &'a mut dyn Write,
+        stderr: &'a mut dyn Write,
+    ) -> Context<'a> {
+        Context {
+            stdin,
+            stdout,
+            stderr,
+            env: Vec::new(),
+            stdout_is_terminal: false,
+            fs: &RealFs::DETACHED,
+        }
+    }
+
+    /// The value of environment variable `name`.
+    pub fn var(&self, name: &str) -> Option<&OsStr> {
+        self.env
+            .iter()
+            .rev()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_os_str())
+    }
+}
+
+pub trait Utility {
+    /// The name it is invoked by, e.g. "cat".
+    fn name(&self) -> &str;
+
+    /// Take the command line arguments, without the program name. An
+    /// error is a message ready to print.
+    fn parse_args(&mut self, args: &[OsString]) -> Result<(), String>;
+
+    /// Do the work and return the exit status.
+    fn execute(&mut self, ctx: &mut Context) -> i32;
+
+    /// `parse_args`, then `execute` unless that failed.
+    fn call(&mut self, args: &[OsString], ctx: &mut Context) -> i32 {
+        match self.parse_args(args) {
+            Ok(()) => self.execute(ctx),
+            Err(message) => {
+                let _ = writeln!(ctx.stderr, "{}", message);
+                1
+            }
+        }
+    }
+}
+
+/// Run `utility` on the process's own streams and environment, returning
+/// the exit status. Output still buffered at the end is written out here;
+/// if that fails, a utility that had succeeded fails with a write error.
+pub fn run_std(utility: &mut dyn Utility, args: &[OsString]) -> i32 {
+    let stdin = io::stdin();
+    let mut stdin_stream = BufReader::new(stdin.lock());
+    let stdout = io::stdout();
+    let stdout_is_terminal = stdout.is_terminal();
+    let mut stdout_stream = BufWriter::new(stdout.lock());
+    let stderr = io::stderr();
+    let mut stderr_stream = stderr.lock();
+    let status = {
+        let mut ctx = Context {
+            stdin: &mut stdin_stream,
+            stdout: &mut stdout_stream,
+            stderr: &mut stderr_stream,
+            env: env::vars_os().collect(),
+            stdout_is_terminal,
+            fs: &RealFs::PROCESS,
+        };
+        utility.call(args, &mut ctx)
+    };
+    match stdout_stream.flush() {
+        Ok(()) => status,
+        // a utility that failed has already said why
+        Err(_) if status != 0 => status,
+        Err(err) => {
+            writeln!(
+                stderr_stream,
+                "{}: write error: {}",
+                utility.name(),
+                describe(&err)
+            )
+            .unwrap();
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Shout;
+
+    impl Utility for Shout {
+        fn name(&self) -> &str {
+            "shout"
+        }
+
+        fn parse_args(&mut self, args: &[OsString]) -> Result<(), String> {
+            match args.first() {
+                Some(arg) => Err(format!("shout: extra operand '{}'", arg.to_string_lossy())),
+                None => Ok(()),
+            }
+        }
+
+        fn execute(&mut self, ctx: &mut Context) -> i32 {
+            let mut line = String::new();
+            ctx.stdin.read_line(&mut line).unwrap();
+            let suffix = ctx
+                .var("SUFFIX")
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            write!(ctx.stdout, "{}{}", line.trim_end().to_uppercase(), suffix).unwrap();
+            0
+        }
+    }
+
+    #[test]
+    fn test_call() {
+        let mut stdin = &b"hello\n"[..];
+        let mut stdout: Vec<u8> = Vec::new();
+        let mut stderr: Vec<u8> = Vec::new();
+        let mut ctx = Context::new(&mut stdin, &mut stdout, &mut stderr);
+        ctx.env.push(("SUFFIX".into(), "!".into()));
+        // the process's standard output is not the one written to here
+        assert_eq!(ctx.fs.stdout(), None);
+        assert_eq!(Shout.call(&[], &mut ctx), 0);
+        assert_eq!(Shout.call(&["x".into()], &mut ctx), 1);
+        assert_eq!(stdout, b"HELLO!".to_vec());
+        assert_eq!(stderr, b"shout: extra operand 'x'\n".to_vec());
+    }
+}
@@ -0,0 +1,469 @@
+//! The filesystem as the applets see it when they look at and open FILE
+//! operands: the real one, or one held in memory for tests, which can be
+//! told to fail in the ways real files sometimes do.
+//!
+//! Only looking at and opening operands goes through it. `cat --follow`
+//! watches and reopens real files, `--state` keeps its offsets in a real
+//! file and resumes by seeking real files, and `--glob` and `-r` list real
+//! directories. An operand held in memory is therefore read once from the
+//! start, and wildcards and recursion never see it.
+use super::input::Input;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// What kind of file a path names.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FileKind {
+    File,
+    Dir,
+    Fifo,
+    Socket,
+    /// devices and anything else
+    Other,
+}
+
+/// The identity of a file, shared by every path and descriptor for it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FileId {
+    dev: u64,
+    ino: u64,
+}
+
+/// What `FileSystem::metadata` tells about a file.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FileInfo {
+    pub kind: FileKind,
+    pub len: u64,
+    /// none where the platform has no file identities
+    pub id: Option<FileId>,
+}
+
+impl FileInfo {
+    /// Whether `self` and `other` are the same file.
+    pub fn same_file(&self, other: &FileInfo) -> bool {
+        self.id.is_some() && self.id == other.id
+    }
+}
+
+pub trait FileSystem {
+    /// Look at `path`, following symbolic links.
+    fn metadata(&self, path: &Path) -> io::Result<FileInfo>;
+
+    /// Open `path` for reading.
+    fn open(&self, path: &Path) -> io::Result<Input>;
+
+    /// The file standard output writes to, if it is one.
+    fn stdout(&self) -> Option<FileInfo>;
+
+    /// Whether opening or reading `path` may wait on another process.
+    fn may_block(&self, path: &Path) -> bool {
+        match self.metadata(path) {
+            Ok(info) => info.kind == FileKind::Fifo || info.kind == FileKind::Socket,
+            Err(_) => false,
+        }
+    }
+}
+
+/// The filesystem of the operating system. Only `RealFs::PROCESS` takes
+/// standard output to be the process's own; a caller writing it somewhere
+/// else, into memory say, uses `RealFs::DETACHED`.
+pub struct RealFs {
+    process_stdout: bool,
+}
+
+impl RealFs {
+    /// standard output is not one of the filesystem's files
+    pub const DETACHED: RealFs = RealFs {
+        process_stdout: false,
+    };
+    /// standard output is file descriptor 1
+    pub const PROCESS: RealFs = RealFs {
+        process_stdout: true,
+    };
+}
+
+// the mode constants are narrower on some platforms
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+fn kind_of_mode(mode: u32) -> FileKind {
+    match mode & libc::S_IFMT as u32 {
+        m if m == libc::S_IFREG as u32 => FileKind::File,
+        m if m == libc::S_IFDIR as u32 => FileKind::Dir,
+        m if m == libc::S_IFIFO as u32 => FileKind::Fifo,
+        m if m == libc::S_IFSOCK as u32 => FileKind::Socket,
+        _ => FileKind::Other,
+    }
+}
+
+impl FileSystem for RealFs {
+    #[cfg(unix)]
+    fn metadata(&self, path: &Path) -> io::Result<FileInfo> {
+        use std::os::unix::fs::MetadataExt;
+        let meta = fs::metadata(path)?;
+        Ok(FileInfo {
+            kind: kind_of_mode(meta.mode()),
+            len: meta.len(),
+            id: Some(FileId {
+                dev: meta.dev(),
+                ino: meta.ino(),
+            }),
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn metadata(&self, path: &Path) -> io::Result<FileInfo> {
+        let meta = fs::metadata(path)?;
+        let kind = if meta.is_dir() {
+            FileKind::Dir
+        } else if meta.is_file() {
+            FileKind::File
+        } else {
+            FileKind::Other
+        };
+        Ok(FileInfo {
+            kind,
+            len: meta.len(),
+            id: None,
+        })
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Input> {
+        Input::open(path)
+    }
+
+    #[cfg(unix)]
+    #[allow(clippy::unnecessary_cast)]
+    fn stdout(&self) -> Option<FileInfo> {
+        if !self.process_stdout {
+            return None;
+        }
+        // SAFETY: fstat only writes to the stat we own
+        let stat = unsafe {
+            let mut stat: libc::stat = std::mem::zeroed();
+            if libc::fstat(libc::STDOUT_FILENO, &mut stat) == -1 {
+                return None;
+            }
+            stat
+        };
+        Some(FileInfo {
+            kind: kind_of_mode(stat.st_mode as u32),
+            len: stat.st_size as u64,
+            id: Some(FileId {
+                dev: stat.st_dev as u64,
+                ino: stat.st_ino as u64,
+            }),
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn stdout(&self) -> Option<FileInfo> {
+        None
+    }
+
+    fn may_block(&self, path: &Path) -> bool {
+        Input::may_block(path)
+    }
+}
+
+/// How reading an in-memory file misbehaves.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+struct Faults {
+    /// opening fails with "Permission denied"
+    denied: bool,
+    /// reading fails with "Input/output error" once this far in
+    error_at: Option<u64>,
+    /// no read returns more bytes than this
+    short_reads: Option<usize>,
+}
+
+struct Node {
+    path: PathBuf,
+    kind: FileKind,
+    ino: u64,
+    data: Vec<u8>,
+    faults: Faults,
+}
+
+/// A filesystem of files and directories in memory. Paths are looked up
+/// as they are given, without resolving `.` or `..`.
+///
+/// ```
+/// use rtw::vfs::{FileSystem, MemoryFs};
+/// use std::io::Read;
+/// use std::path::Path;
+///
+/// let mut fs = MemoryFs::new();
+/// fs.with_file("log", b"one\ntwo\n").with_error_at("log", 4);
+/// let mut text = String::new();
+/// let err = fs.open(Path::new("log")).unwrap().read_to_string(&mut text);
+/// assert_eq!(text, "one\n");
+/// assert_eq!(err.unwrap_err().to_string(), "Input/output error");
+/// ```
+#[derive(Default)]
+pub struct MemoryFs {
+    nodes: Vec<Node>,
+    stdout: Option<PathBuf>,
+}
+
+fn error(kind: io::ErrorKind, message: &str) -> io::Error {
+    io::Error::new(kind, message.to_string())
+}
+
+impl MemoryFs {
+    pub fn new() -> MemoryFs {
+        MemoryFs::default()
+    }
+
+    fn add(&mut self, path: &Path, kind: FileKind, data: &[u8]) -> &mut Self {
+        self.nodes.retain(|node| node.path != path);
+        let ino = self.nodes.iter().map(|node| node.ino).max().unwrap_or(0) + 1;
+        self.nodes.push(Node {
+            path: path.to_path_buf(),
+            kind,
+            ino,
+            data: data.to_vec(),
+            faults: Faults::default(),
+        });
+        self
+    }
+
+    /// Add a regular file holding `data`, replacing whatever was at `path`.
+    pub fn with_file<P: AsRef<Path>>(&mut self, path: P, data: &[u8]) -> &mut Self {
+        self.add(path.as_ref(), FileKind::File, data)
+    }
+
+    pub fn with_dir<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.add(path.as_ref(), FileKind::Dir, b"")
+    }
+
+    /// Add a FIFO which gives `data` and then ends.
+    pub fn with_fifo<P: AsRef<Path>>(&mut self, path: P, data: &[u8]) -> &mut Self {
+        self.add(path.as_ref(), FileKind::Fifo, data)
+    }
+
+    fn faults(&mut self, path: &Path) -> &mut Faults {
+        match self.nodes.iter_mut().find(|node| node.path == path) {
+            Some(node) => &mut node.faults,
+            None => panic!("{}: not in the filesystem", path.display()),
+        }
+    }
+
+    /// Make opening `path` fail as if it were not readable (EACCES).
+    pub fn with_denied<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.faults(path.as_ref()).denied = true;
+        self
+    }
+
+    /// Make reading `path` fail with EIO on reaching `offset`.
+    pub fn with_error_at<P: AsRef<Path>>(&mut self, path: P, offset: u64) -> &mut Self {
+        self.faults(path.as_ref()).error_at = Some(offset);
+        self
+    }
+
+    /// Make each read of `path` return at most `max` bytes.
+    pub fn with_short_reads<P: AsRef<Path>>(&mut self, path: P, max: usize) -> &mut Self {
+        self.faults(path.as_ref()).short_reads = Some(max.max(1));
+        self
+    }
+
+    /// Have standard output write to the file at `path`, as `>>path` does.
+    pub fn with_stdout<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.stdout = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    fn node(&self, path: &Path) -> io::Result<&Node> {
+        self.nodes
+            .iter()
+            .find(|node| node.path == path)
+            .ok_or_else(|| error(io::ErrorKind::NotFound, "No such file or directory"))
+    }
+
+    fn info(node: &Node) -> FileInfo {
+        FileInfo {
+            kind: node.kind,
+            len: node.data.len() as u64,
+            id: Some(FileId {
+                dev: 0,
+                ino: node.ino,
+            }),
+        }
+    }
+}
+
+impl FileSystem for MemoryFs {
+    fn metadata(&self, path: &Path) -> io::Result<FileInfo> {
+        self.node(path).map(MemoryFs::info)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Input> {
+        let node = self.node(path)?;
+        if node.faults.denied {
+            return Err(error(io::ErrorKind::PermissionDenied, "Permission denied"));
+        }
+        let mut faults = node.faults;
+        if node.kind == FileKind::Dir {
+            // opening a directory works; reading it does not
+            faults.error_at = Some(0);
+        }
+        Ok(Input::Memory(MemoryFile {
+            data: node.data.clone(),
+            pos: 0,
+            faults,
+            dir: node.kind == FileKind::Dir,
+        }))
+    }
+
+    fn stdout(&self) -> Option<FileInfo> {
+        let path = self.stdout.as_ref()?;
+        self.node(path).ok().map(MemoryFs::info)
+    }
+}
+
+/// A file of a `MemoryFs`, opened.
+pub struct MemoryFile {
+    data: Vec<u8>,
+    pos: usize,
+    faults: Faults,
+    dir: bool,
+}
+
+impl Read for MemoryFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut end = self.data.len();
+        if let Some(offset) = self.faults.error_at {
+            if self.pos as u64 >= offset {
+                return Err(if self.dir {
+                    error(io::ErrorKind::Other, "Is a directory")
+                } else {
+                    error(io::ErrorKind::Other, "Input/output error")
+                });
+            }
+            end = end.min(offset as usize);
+        }
+        let mut n = end.saturating_sub(self.pos).min(buf.len());
+        if let Some(max) = self.faults.short_reads {
+            n = n.min(max);
+        }
+        buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(fs: &dyn FileSystem, path: &str) -> (Vec<u8>, Vec<usize>, Option<String>) {
+        let mut input = fs.open(Path::new(path)).unwrap();
+        let mut data = Vec::new();
+        let mut reads = Vec::new();
+        let mut buf = [0u8; 8];
+        loop {
+            match input.read(&mut buf) {
+                Ok(0) => return (data, reads, None),
+                Ok(n) => {
+                    data.extend_from_slice(&buf[..n]);
+                    reads.push(n);
+                }
+                Err(err) => return (data, reads, Some(err.to_string())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_memory_fs() {
+        let mut fs = MemoryFs::new();
+        fs.with_file("a", b"0123456789abc")
+            .with_file("slow", b"0123456789")
+            .with_short_reads("slow", 3)
+            .with_file("bad", b"0123456789")
+            .with_error_at("bad", 5)
+            .with_file("secret", b"x")
+            .with_denied("secret")
+            .with_dir("dir")
+            .with_fifo("pipe", b"p");
+
+        assert_eq!(
+            read_all(&fs, "a"),
+            (b"0123456789abc".to_vec(), vec![8, 5], None)
+        );
+        assert_eq!(
+            read_all(&fs, "slow"),
+            (b"0123456789".to_vec(), vec![3, 3, 3, 1], None)
+        );
+        assert_eq!(
+            read_all(&fs, "bad"),
+            (
+                b"01234".to_vec(),
+                vec![5],
+                Some("Input/output error".to_string())
+            )
+        );
+        assert_eq!(
+            read_all(&fs, "dir"),
+            (Vec::new(), Vec::new(), Some("Is a directory".to_string()))
+        );
+
+        let err = fs.open(Path::new("secret")).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(err.to_string(), "Permission denied");
+        let err = fs.metadata(Path::new("none")).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(fs.open(Path::new("none")).is_err());
+
+        assert_eq!(fs.metadata(Path::new("dir")).unwrap().kind, FileKind::Dir);
+        assert!(fs.may_block(Path::new("pipe")));
+        assert!(!fs.may_block(Path::new("a")));
+
+        // every file is its own, and standard output goes nowhere yet
+        let a = fs.metadata(Path::new("a")).unwrap();
+        assert!(a.same_file(&fs.metadata(Path::new("a")).unwrap()));
+        assert!(!a.same_file(&fs.metadata(Path::new("slow")).unwrap()));
+        assert_eq!(fs.stdout(), None);
+        fs.with_stdout("a");
+        assert!(fs.stdout().unwrap().same_file(&a));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_real_fs() {
+        let path = Path::new("ci-tests/test-data/cat_test.txt");
+        let info = RealFs::DETACHED.metadata(path).unwrap();
+        assert_eq!(info.kind, FileKind::File);
+        assert_eq!(info.len, fs::metadata(path).unwrap().len());
+        assert!(info.same_file(
+            &RealFs::DETACHED
+                .metadata(Path::new("ci-tests/../ci-tests/test-data/cat_test.txt"))
+                .unwrap()
+        ));
+        assert_eq!(
+            RealFs::DETACHED
+                .metadata(Path::new("ci-tests"))
+                .unwrap()
+                .kind,
+            FileKind::Dir
+        );
+        assert_eq!(
+            RealFs::DETACHED
+                .metadata(Path::new("ci-tests/none"))
+                .err()
+                .unwrap()
+                .kind(),
+            io::ErrorKind::NotFound
+        );
+        let mut text = String::new();
+        RealFs::DETACHED
+            .open(path)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert!(text.starts_with("abcedfg\n"));
+
+        // the process's own standard output is cargo's pipe or file
+        assert_eq!(RealFs::DETACHED.stdout(), None);
+        assert!(RealFs::PROCESS.stdout().is_some());
+    }
+}
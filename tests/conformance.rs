@@ -0,0 +1,366 @@
+//! Runs the applets over the fixtures in ci-tests with many
+//! option combinations and compares stdout, stderr and exit status with
+//! the GNU coreutils tool of the same name. A tool that is not installed,
+//! or is not the GNU one, is skipped with a note.
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+const FIXTURES: &[&str] = &[
+    "ci-tests/test-data/cat_test.txt",
+    "ci-tests/fixtures/blank_lines.txt",
+    "ci-tests/fixtures/no_newline.txt",
+    "ci-tests/fixtures/all_bytes.bin",
+    "ci-tests/fixtures/empty.txt",
+];
+
+/// The short options GNU and rtw cat have in common.
+const CAT_FLAGS: &[char] = &['A', 'b', 'e', 'E', 'n', 's', 't', 'T', 'u', 'v'];
+
+/// The system's GNU `name`, if it has one.
+fn gnu_tool(name: &str) -> Option<PathBuf> {
+    let path = ["/usr/bin", "/bin"]
+        .iter()
+        .map(|dir| Path::new(dir).join(name))
+        .find(|path| path.is_file())?;
+    let version = Command::new(&path).arg("--version").output().ok()?;
+    if String::from_utf8_lossy(&version.stdout).contains("GNU coreutils") {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn run(program: &Path, pre: &[&str], args: &[String], stdin: &[u8]) -> Output {
+    run_to(program, pre, args, stdin, Stdio::piped())
+}
+
+/// `run` with standard output sent to `stdout`.
+fn run_to(program: &Path, pre: &[&str], args: &[String], stdin: &[u8], stdout: Stdio) -> Output {
+    let mut child = Command::new(program)
+        .args(pre)
+        .args(args)
+        .env_remove("POSIXLY_CORRECT")
+        .stdin(Stdio::piped())
+        .stdout(stdout)
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute command");
+    // an applet may exit without reading its input
+    let _ = child.stdin.take().unwrap().write_all(stdin);
+    child.wait_with_output().expect("Failed to execute command")
+}
+
+/// Compares the applet `name`, run both as its own binary and through
+/// `rtw`, with GNU's, collecting every difference.
+struct Conformance {
+    name: &'static str,
+    gnu: PathBuf,
+    binaries: Vec<(PathBuf, Vec<&'static str>)>,
+    failures: Vec<String>,
+    runs: usize,
+}
+
+impl Conformance {
+    fn new(name: &'static str, bin: &str) -> Option<Conformance> {
+        let gnu = match gnu_tool(name) {
+            Some(gnu) => gnu,
+            None => {
+                eprintln!("skipping {}: GNU coreutils {} not found", name, name);
+                return None;
+            }
+        };
+        Some(Conformance {
+            name,
+            gnu,
+            binaries: vec![
+                (PathBuf::from(bin), Vec::new()),
+                (PathBuf::from(env!("CARGO_BIN_EXE_rtw")), vec![name]),
+            ],
+            failures: Vec::new(),
+            runs: 0,
+        })
+    }
+
+    fn check(&mut self, args: &[String], stdin: &[u8]) {
+        self.check_to(args, stdin, || Some(Stdio::piped()));
+    }
+
+    /// `check` with standard output on a full disk, where there is one.
+    fn check_full(&mut self, args: &[String]) {
+        self.check_to(args, b"", || {
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open("/dev/full")
+                .ok()
+                .map(Stdio::from)
+        });
+    }
+
+    fn check_to<F: Fn() -> Option<Stdio>>(&mut self, args: &[String], stdin: &[u8], stdout: F) {
+        let expects = match stdout() {
+            Some(stdout) => run_to(&self.gnu, &[], args, stdin, stdout),
+            None => return,
+        };
+        let gnu_prefix = format!("{}:", self.gnu.display());
+        let expects_err = String::from_utf8_lossy(&expects.stderr)
+            .replace(&gnu_prefix, &format!("{}:", self.name))
+            .replace(
+                &format!("'{}", self.gnu.display()),
+                &format!("'{}", self.name),
+            );
+        for (binary, pre) in &self.binaries {
+            let output = run_to(binary, pre, args, stdin, stdout().unwrap());
+            self.runs += 1;
+            let err = String::from_utf8_lossy(&output.stderr);
+            if output.stdout != expects.stdout
+                || err != expects_err
+                || output.status.code() != expects.status.code()
+            {
+                self.failures.push(format!(
+                    "{} {:?}\n  stdout: {:?}\n  expect: {:?}\n  stderr: {:?}\n  expect: {:?}\n  \
+                     status: {:?}, expect: {:?}",
+                    pre.first().map(|_| "rtw").unwrap_or(self.name),
+                    pre.iter()
+                        .map(|s| s.to_string())
+                        .chain(args.iter().cloned())
+                        .collect::<Vec<_>>(),
+                    String::from_utf8_lossy(&output.stdout),
+                    String::from_utf8_lossy(&expects.stdout),
+                    err,
+                    expects_err,
+                    output.status.code(),
+                    expects.status.code()
+                ));
+            }
+        }
+    }
+
+    fn finish(self) {
+        eprintln!(
+            "{}: {} runs compared with {}",
+            self.name,
+            self.runs,
+            self.gnu.display()
+        );
+        assert!(
+            self.failures.is_empty(),
+            "{} of {} runs differ from GNU {}:\n{}",
+            self.failures.len(),
+            self.runs,
+            self.name,
+            self.failures.join("\n")
+        );
+    }
+}
+
+fn strings(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn test_cat() {
+    let mut conformance = match Conformance::new("cat", env!("CARGO_BIN_EXE_cat")) {
+        Some(conformance) => conformance,
+        None => return,
+    };
+    let operands = strings(FIXTURES);
+
+    // every combination of flags over every fixture, both separate and
+    // clustered, and with options after the operands
+    for mask in 0..1u32 << CAT_FLAGS.len() {
+        let flags: Vec<char> = (0..CAT_FLAGS.len())
+            .filter(|i| mask & (1 << i) != 0)
+            .map(|i| CAT_FLAGS[i])
+            .collect();
+        let mut args: Vec<String> = flags.iter().map(|flag| format!("-{}", flag)).collect();
+        args.extend(operands.iter().cloned());
+        conformance.check(&args, b"");
+        if flags.len() > 1 {
+            let mut args = vec![flags.iter().fold("-".to_string(), |mut s, &flag| {
+                s.push(flag);
+                s
+            })];
+            args.extend(operands.iter().cloned());
+            conformance.check(&args, b"");
+        }
+        if flags.len() == 1 {
+            let mut args = operands.clone();
+            args.push(format!("-{}", flags[0]));
+            conformance.check(&args, b"");
+        }
+    }
+
+    // long options, each fixture on its own, standard input and errors
+    let long: &[&[&str]] = &[
+        &["--show-all"],
+        &["--number-nonblank", "--show-ends"],
+        &["--number", "--squeeze-blank"],
+        &["--show-tabs", "--show-nonprinting"],
+        &["-u", "--number", "-s"],
+    ];
+    for options in long {
+        for fixture in FIXTURES {
+            let mut args = strings(options);
+            args.push(fixture.to_string());
+            conformance.check(&args, b"");
+        }
+    }
+    let stdin = std::fs::read(FIXTURES[0]).unwrap();
+    for args in &[
+        &[][..],
+        &["-"],
+        &["-n", "-", FIXTURES[2], "-"],
+        &["-bE", FIXTURES[1], "-"],
+        &["-n", "--", "-"],
+        &["--", "-n"],
+        &["-A", "ci-tests/test-data/missing.txt", FIXTURES[2]],
+        &["-n", "ci-tests", FIXTURES[2]],
+        &["-nx", FIXTURES[0]],
+        &[FIXTURES[0], FIXTURES[0]],
+        &["-n", FIXTURES[0], "-", FIXTURES[0]],
+        &["-n", "--bogus", FIXTURES[0]],
+    ] {
+        conformance.check(&strings(args), &stdin);
+    }
+    conformance.check_full(&strings(&[FIXTURES[0]]));
+    conformance.finish();
+}
+
+/// `--lines` keeps the numbers lines have in the whole file: the same as
+/// numbering all of it with GNU cat and cutting the range out with sed.
+#[test]
+fn test_cat_line_numbers() {
+    let gnu = match gnu_tool("cat") {
+        Some(gnu) => gnu,
+        None => {
+            eprintln!("skipping cat --lines: GNU coreutils cat not found");
+            return;
+        }
+    };
+    let mut failures = Vec::new();
+    for fixture in FIXTURES {
+        for flags in ["-n", "-b", "-nE", "-bT"].iter() {
+            for range in ["1:3", "6:12", "9:14", "20:40", "5:", ":2"].iter() {
+                let args = strings(&[flags, &format!("--lines={}", range), fixture]);
+                let output = run(Path::new(env!("CARGO_BIN_EXE_cat")), &[], &args, b"");
+                let (start, end) = range.split_at(range.find(':').unwrap());
+                let script = format!(
+                    "{} {} {} | sed -n '{},{}p'",
+                    gnu.display(),
+                    flags,
+                    fixture,
+                    if start.is_empty() { "1" } else { start },
+                    if end.len() > 1 { &end[1..] } else { "$" }
+                );
+                let expects = Command::new("sh")
+                    .arg("-c")
+                    .arg(&script)
+                    .output()
+                    .expect("Failed to execute sh");
+                if output.stdout != expects.stdout {
+                    failures.push(format!(
+                        "cat {:?}\n  stdout: {:?}\n  expect: {:?} ({})",
+                        args,
+                        String::from_utf8_lossy(&output.stdout),
+                        String::from_utf8_lossy(&expects.stdout),
+                        script
+                    ));
+                }
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn test_echo() {
+    let mut conformance = match Conformance::new("echo", env!("CARGO_BIN_EXE_echo")) {
+        Some(conformance) => conformance,
+        None => return,
+    };
+    let operands: &[&[&str]] = &[
+        &["plain", "two  words"],
+        &["tab\\there\\n", "\\0101\\x41\\e[0m", "\\c", "gone"],
+        &["-n", "-e", "--", "-"],
+        &["\\\\", "trailing\\"],
+    ];
+    let flags = ["n", "e", "E"];
+    for mask in 0..1u32 << flags.len() {
+        let set: Vec<&str> = (0..flags.len())
+            .filter(|i| mask & (1 << i) != 0)
+            .map(|i| flags[i])
+            .collect();
+        for strings in operands {
+            let mut args: Vec<String> = set.iter().map(|flag| format!("-{}", flag)).collect();
+            args.extend(strings.iter().map(|s| s.to_string()));
+            conformance.check(&args, b"");
+            // clustered, in both orders
+            let mut args = vec![format!("-{}", set.concat())];
+            args.extend(strings.iter().map(|s| s.to_string()));
+            conformance.check(&args, b"");
+            let mut args = vec![format!(
+                "-{}",
+                set.iter().rev().cloned().collect::<String>()
+            )];
+            args.extend(strings.iter().map(|s| s.to_string()));
+            conformance.check(&args, b"");
+        }
+    }
+    for args in &[
+        &["-nx", "y"][..],
+        &["--version", "x"],
+        &["--help", "x"],
+        &[],
+    ] {
+        conformance.check(&strings(args), b"");
+    }
+    conformance.check_full(&strings(&["hello"]));
+    conformance.finish();
+}
+
+#[test]
+fn test_printf() {
+    let mut conformance = match Conformance::new("printf", env!("CARGO_BIN_EXE_printf")) {
+        Some(conformance) => conformance,
+        None => return,
+    };
+    let formats = [
+        "%d|%i|%o|%u|%x|%X\\n",
+        "%5d|%-5d|%05d|%+d|% d|%.3d\\n",
+        "%#x|%#o|%#X|%*d|%-*d\\n",
+        "%f|%e|%g|%E|%G\\n",
+        "%.2f|%10.3e|%-10g|%+.0f|%#.0e\\n",
+        "%s|%5s|%-5s|%.1s|%c\\n",
+        "%b|%q\\n",
+        "[%s]\\n",
+        "no conversions\\n",
+    ];
+    let arguments: &[&[&str]] = &[
+        &[],
+        &["0"],
+        &["42", "-7", "0x1F", "010", "'A", "3"],
+        &["3.5", "-0.001", "1e10", "inf", "nan"],
+        &["word", "a b", "", "it's", "\\t\\0101"],
+        &["12abc", "x", "9999999999999999999999"],
+        &["-", "+", "  ", " -"],
+    ];
+    for format in formats.iter() {
+        for args in arguments {
+            let mut list = vec![format.to_string()];
+            list.extend(args.iter().map(|arg| arg.to_string()));
+            conformance.check(&list, b"");
+        }
+    }
+    for args in &[
+        &["%z"][..],
+        &["%5b", "x"],
+        &["\\x"],
+        &["--", "%s\\n", "x"],
+        &[],
+    ] {
+        conformance.check(&strings(args), b"");
+    }
+    conformance.check_full(&strings(&["%s\\n", "x"]));
+    conformance.finish();
+}
@@ -0,0 +1,43 @@
+//! Runs each fuzz target over its regression corpus in fuzz/regressions,
+//! so that inputs which once crashed stay fixed under plain `cargo test`.
+//! Add a file there for every crash the fuzzer finds.
+extern crate rtw;
+
+use rtw::fuzzing;
+use std::fs;
+use std::panic;
+use std::path::Path;
+
+fn replay(target: &str, run: fn(&[u8])) {
+    let dir = Path::new("fuzz/regressions").join(target);
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap_or_else(|err| panic!("{}: {}", dir.display(), err))
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "{}: no inputs", dir.display());
+    let failed: Vec<String> = paths
+        .iter()
+        .filter(|path| {
+            let data = fs::read(path).unwrap();
+            panic::catch_unwind(|| run(&data)).is_err()
+        })
+        .map(|path| path.display().to_string())
+        .collect();
+    assert!(failed.is_empty(), "{} failed on {:?}", target, failed);
+}
+
+#[test]
+fn test_parse_args() {
+    replay("parse_args", fuzzing::parse_args);
+}
+
+#[test]
+fn test_cat_run() {
+    replay("cat_run", fuzzing::cat_run);
+}
+
+#[test]
+fn test_printf() {
+    replay("printf", fuzzing::printf);
+}
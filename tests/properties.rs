@@ -0,0 +1,104 @@
+//! Property-based tests: the fuzz entry points on random input, and cat
+//! compared with the system's GNU cat on random options and bytes.
+extern crate proptest;
+extern crate rtw;
+
+use proptest::prelude::*;
+use rtw::cat::CatBuilder;
+use rtw::fuzzing;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// The short options GNU and rtw cat have in common.
+const CAT_FLAGS: &[&str] = &["-A", "-b", "-e", "-E", "-n", "-s", "-t", "-T", "-u", "-v"];
+
+/// Bytes that steer cat's state machine: line ends, tabs, blanks and
+/// every class of nonprinting byte.
+fn interesting_byte() -> impl Strategy<Value = u8> {
+    prop_oneof![
+        3 => Just(b'\n'),
+        1 => Just(b'\t'),
+        1 => Just(b' '),
+        2 => Just(b'a'),
+        1 => prop::sample::select(vec![0x00, 0x01, 0x1b, 0x7f, 0x80, 0x9b, 0xa0, 0xc3, 0xff]),
+        1 => any::<u8>(),
+    ]
+}
+
+fn gnu_cat() -> Option<&'static Path> {
+    let path = Path::new("/usr/bin/cat");
+    let version = Command::new(path).arg("--version").output().ok()?;
+    if String::from_utf8_lossy(&version.stdout).contains("GNU coreutils") {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn cat_run_never_panics(data in prop::collection::vec(interesting_byte(), 3..512)) {
+        fuzzing::cat_run(&data);
+    }
+
+    #[test]
+    fn parse_args_never_panics(
+        args in prop::collection::vec(
+            prop::collection::vec(prop_oneof![Just(b'-'), Just(b'='), any::<u8>()], 0..12),
+            0..6,
+        )
+    ) {
+        fuzzing::parse_args(&args.join(&0));
+    }
+
+    #[test]
+    fn printf_never_panics(
+        args in prop::collection::vec(
+            prop::collection::vec(
+                prop_oneof![Just(b'%'), Just(b'\\'), Just(b'*'), Just(b'.'), any::<u8>()],
+                0..12,
+            ),
+            1..5,
+        )
+    ) {
+        fuzzing::printf(&args.join(&0));
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn cat_matches_gnu(
+        mask in 0u32..1 << 10,
+        input in prop::collection::vec(interesting_byte(), 0..256),
+    ) {
+        let gnu = match gnu_cat() {
+            Some(gnu) => gnu,
+            None => return Ok(()),
+        };
+        let args: Vec<&str> = (0..CAT_FLAGS.len())
+            .filter(|i| mask & (1 << i) != 0)
+            .map(|i| CAT_FLAGS[i])
+            .collect();
+
+        let mut cat = CatBuilder::new().build();
+        cat.parse(&args).unwrap();
+        let mut out: Vec<u8> = Vec::new();
+        let mut err: Vec<u8> = Vec::new();
+        cat.run(&mut &input[..], &mut out, &mut err).unwrap();
+
+        let mut child = Command::new(gnu)
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(&input).unwrap();
+        let expects = child.wait_with_output().unwrap();
+        prop_assert_eq!(out, expects.stdout, "cat {:?}", args);
+    }
+}
@@ -0,0 +1,361 @@
+//! Golden-file tests in the style of cram: each ci-tests/transcripts/*.t
+//! file is a shell session to replay.
+//!
+//!     Unindented lines are comments.
+//!       $ rtw cat -n cat_test.txt | head -n 1
+//!            1\tabcedfg (esc)
+//!       $ rtw cat missing.txt
+//!       cat: missing.txt: No such file or directory
+//!       [1]
+//!
+//! A command is a line indented by two spaces and `$ `, continued by lines
+//! starting `  > `. The lines indented by two spaces below it are its
+//! expected output, standard error included, and `[N]` its exit status if
+//! not 0. An output line ending ` (no-eol)` lacks its newline; one ending
+//! ` (esc)` is written with `\t`, `\r`, `\\` and `\xHH` escapes.
+//!
+//! Commands run under `sh` in a fresh copy of ci-tests/test-data, with
+//! `rtw` and links to each of its applets first in PATH. With
+//! RTW_UPDATE_TRANSCRIPTS set, the files are rewritten with the output
+//! the commands give now instead of being checked.
+#![cfg(unix)]
+
+extern crate rtw;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const TRANSCRIPTS: &str = "ci-tests/transcripts";
+const TEST_DATA: &str = "ci-tests/test-data";
+
+/// A line of a transcript file.
+#[derive(Debug, PartialEq)]
+enum Line {
+    Comment(String),
+    Command(String),
+    Output(Vec<u8>),
+    Status(i32),
+}
+
+/// One command of a transcript, with the lines after it.
+#[derive(Debug, PartialEq)]
+struct Case {
+    /// the comments before it
+    comments: Vec<String>,
+    command: String,
+    output: Vec<u8>,
+    status: i32,
+}
+
+fn parse_line(line: &str) -> Line {
+    let indented = match line.strip_prefix("  ") {
+        Some(indented) => indented,
+        None => return Line::Comment(line.to_string()),
+    };
+    let (text, newline) = match indented.strip_suffix(" (no-eol)") {
+        Some(text) => (text, false),
+        None => (indented, true),
+    };
+    let escaped = text.strip_suffix(" (esc)");
+    if escaped.is_none() && newline {
+        if let Some(command) = text.strip_prefix("$ ") {
+            return Line::Command(command.to_string());
+        }
+        if let Some(status) = status(text) {
+            return Line::Status(status);
+        }
+    }
+    let mut output = match escaped {
+        Some(text) => unescape(text),
+        None => text.as_bytes().to_vec(),
+    };
+    if newline {
+        output.push(b'\n');
+    }
+    Line::Output(output)
+}
+
+/// The exit status in a `[N]` line.
+fn status(text: &str) -> Option<i32> {
+    text.strip_prefix('[')?.strip_suffix(']')?.parse().ok()
+}
+
+fn unescape(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1)) {
+            (b'\\', Some(b't')) => out.push(b'\t'),
+            (b'\\', Some(b'r')) => out.push(b'\r'),
+            (b'\\', Some(b'\\')) => out.push(b'\\'),
+            (b'\\', Some(b'x')) => {
+                let hex = text.get(i + 2..i + 4).unwrap_or("");
+                out.push(u8::from_str_radix(hex, 16).expect("bad \\x escape"));
+                i += 4;
+                continue;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+                continue;
+            }
+        }
+        i += 2;
+    }
+    out
+}
+
+/// `output` as transcript lines, the inverse of `parse_line`.
+fn render_output(output: &[u8]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for chunk in output.split_inclusive(|&b| b == b'\n') {
+        let (text, newline) = match chunk.strip_suffix(b"\n") {
+            Some(text) => (text, true),
+            None => (chunk, false),
+        };
+        // anything that would read back as something else is escaped
+        let plain = std::str::from_utf8(text).ok().filter(|s| {
+            !s.chars().any(|c| c.is_control())
+                && !s.starts_with("$ ")
+                && !s.starts_with("> ")
+                && status(s).is_none()
+                && !s.ends_with(" (esc)")
+                && !s.ends_with(" (no-eol)")
+        });
+        let mut line = match plain {
+            Some(s) => format!("  {}", s),
+            None => {
+                let mut line = String::from("  ");
+                for &b in text {
+                    match b {
+                        b'\t' => line.push_str("\\t"),
+                        b'\r' => line.push_str("\\r"),
+                        b'\\' => line.push_str("\\\\"),
+                        0x20..=0x7e => line.push(b as char),
+                        _ => line.push_str(&format!("\\x{:02x}", b)),
+                    }
+                }
+                line.push_str(" (esc)");
+                line
+            }
+        };
+        if !newline {
+            line.push_str(" (no-eol)");
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn parse(text: &str) -> (Vec<Case>, Vec<String>) {
+    let mut cases: Vec<Case> = Vec::new();
+    let mut comments = Vec::new();
+    for line in text.lines() {
+        match parse_line(line) {
+            Line::Comment(comment) => comments.push(comment),
+            Line::Command(command) => cases.push(Case {
+                comments: std::mem::take(&mut comments),
+                command,
+                output: Vec::new(),
+                status: 0,
+            }),
+            Line::Output(output) => {
+                let case = cases.last_mut().expect("output before any command");
+                let continued = case.output.is_empty() && case.status == 0;
+                match output.strip_prefix(b"> ").filter(|_| continued) {
+                    Some(rest) => {
+                        // a continued command
+                        case.command.push('\n');
+                        case.command
+                            .push_str(String::from_utf8_lossy(rest).trim_end_matches('\n'));
+                    }
+                    None => case.output.extend_from_slice(&output),
+                }
+            }
+            Line::Status(status) => {
+                cases.last_mut().expect("status before any command").status = status
+            }
+        }
+    }
+    (cases, comments)
+}
+
+fn render(cases: &[Case], trailing: &[String]) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for case in cases {
+        lines.extend(case.comments.iter().cloned());
+        let mut command = case.command.lines();
+        lines.push(format!("  $ {}", command.next().unwrap_or("")));
+        lines.extend(command.map(|line| format!("  > {}", line)));
+        lines.extend(render_output(&case.output));
+        if case.status != 0 {
+            lines.push(format!("  [{}]", case.status));
+        }
+    }
+    lines.extend(trailing.iter().cloned());
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// A scratch directory with the test data, and a bin directory of links
+/// to `rtw`.
+struct Sandbox {
+    root: PathBuf,
+}
+
+impl Sandbox {
+    fn new(name: &str) -> Sandbox {
+        let root = env::temp_dir().join(format!("rtw-transcript-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        copy_dir(Path::new(TEST_DATA), &root.join("work")).unwrap();
+        let bin = root.join("bin");
+        fs::create_dir_all(&bin).unwrap();
+        let rtw = Path::new(env!("CARGO_BIN_EXE_rtw"));
+        for link in rtw::multicall::install(&bin, rtw) {
+            link.unwrap();
+        }
+        std::os::unix::fs::symlink(rtw, bin.join("rtw")).unwrap();
+        Sandbox { root }
+    }
+
+    fn run(&self, command: &str) -> (Vec<u8>, i32) {
+        let path = match env::var_os("PATH") {
+            Some(path) => {
+                let mut paths = vec![self.root.join("bin")];
+                paths.extend(env::split_paths(&path));
+                env::join_paths(paths).unwrap()
+            }
+            None => self.root.join("bin").into_os_string(),
+        };
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(format!("exec 2>&1\n{}", command))
+            .current_dir(self.root.join("work"))
+            .env("PATH", path)
+            .env("LC_ALL", "C")
+            .env_remove("POSIXLY_CORRECT")
+            .env_remove("NO_COLOR")
+            .env_remove("RTW_CAT_COLORS")
+            .output()
+            .expect("Failed to execute sh");
+        (output.stdout, output.status.code().unwrap_or(-1))
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+#[test]
+fn test_transcripts() {
+    let update = env::var_os("RTW_UPDATE_TRANSCRIPTS").is_some();
+    let mut paths: Vec<PathBuf> = fs::read_dir(TRANSCRIPTS)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map(|ext| ext == "t").unwrap_or(false))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no transcripts in {}", TRANSCRIPTS);
+
+    let mut failures = Vec::new();
+    for path in &paths {
+        let text = fs::read_to_string(path).unwrap();
+        let (cases, trailing) = parse(&text);
+        let name = path.file_stem().unwrap().to_string_lossy();
+        let sandbox = Sandbox::new(&name);
+        let actual: Vec<Case> = cases
+            .iter()
+            .map(|case| {
+                let (output, status) = sandbox.run(&case.command);
+                Case {
+                    comments: case.comments.clone(),
+                    command: case.command.clone(),
+                    output,
+                    status,
+                }
+            })
+            .collect();
+        let rendered = render(&actual, &trailing);
+        if rendered == text {
+            continue;
+        }
+        if update {
+            fs::write(path, rendered).unwrap();
+            eprintln!("updated {}", path.display());
+        } else {
+            failures.push(format!(
+                "--- {}\n+++ actual\n{}",
+                path.display(),
+                diff(&text, &rendered)
+            ));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "transcripts differ (rerun with RTW_UPDATE_TRANSCRIPTS=1 to accept):\n{}",
+        failures.join("\n")
+    );
+}
+
+/// The lines of `expected` and `actual` from the first that differs.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let same = expected
+        .iter()
+        .zip(&actual)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut out = format!("@@ line {} @@\n", same + 1);
+    for line in &expected[same..] {
+        out.push_str(&format!("-{}\n", line));
+    }
+    for line in &actual[same..] {
+        out.push_str(&format!("+{}\n", line));
+    }
+    out
+}
+
+#[test]
+fn test_round_trip() {
+    let text = "Comment.\n  $ printf 'a\\tb\\001'\n  > echo\n  a\\tb\\x01 (esc)\n  [2]\n  $ true\n  x (no-eol)\ntrailing\n";
+    let (cases, trailing) = parse(text);
+    assert_eq!(
+        cases,
+        vec![
+            Case {
+                comments: vec!["Comment.".to_string()],
+                command: "printf 'a\\tb\\001'\necho".to_string(),
+                output: b"a\tb\x01\n".to_vec(),
+                status: 2,
+            },
+            Case {
+                comments: Vec::new(),
+                command: "true".to_string(),
+                output: b"x".to_vec(),
+                status: 0,
+            },
+        ]
+    );
+    assert_eq!(trailing, vec!["trailing".to_string()]);
+    assert_eq!(render(&cases, &trailing), text);
+}